@@ -15,6 +15,8 @@ uniform sampler2D tx_floor;
 uniform sampler2D tx_ceiling;
 uniform sampler2D tx_wall;
 uniform sampler2D tx_gate;
+uniform float light;
+uniform float flash;
 uniform int bPause;
 
 out vec4 color;
@@ -32,6 +34,8 @@ void main() {
         color = vec4(1.0, 1.0, 1.0, 1.0);
     }
 
+    color.rgb *= light + flash;
+
     if (bPause == 1) {
         color *= vec4(0.2, 0.2, 0.2, 1.0);
     }
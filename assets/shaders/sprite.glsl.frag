@@ -0,0 +1,26 @@
+#version 450
+
+in VS_OUT {
+    vec2 uv;
+} fs_in;
+
+uniform sampler2D tx_sprite;
+uniform float light;
+uniform float flash;
+uniform int bPause;
+
+out vec4 color;
+
+void main() {
+    color = texture(tx_sprite, fs_in.uv);
+    // Alpha tested, so the depth buffer stays right without sorting
+    if (color.a < 0.5) {
+        discard;
+    }
+
+    color.rgb *= light + flash;
+
+    if (bPause == 1) {
+        color *= vec4(0.2, 0.2, 0.2, 1.0);
+    }
+}
@@ -0,0 +1,22 @@
+#version 450
+
+layout (location = 0) in vec2 corner;
+
+uniform mat4 view;
+uniform mat4 projection;
+uniform vec3 position;
+uniform vec3 right;
+uniform vec2 size;
+uniform int bMirrored;
+
+out VS_OUT {
+    vec2 uv;
+} vs_out;
+
+void main() {
+    vec3 world = position + right * corner.x * size.x + vec3(0.0, corner.y * size.y, 0.0);
+    gl_Position = projection * view * vec4(world, 1.0);
+
+    float s = corner.x + 0.5;
+    vs_out.uv = vec2(bMirrored == 1 ? 1.0 - s : s, 1.0 - corner.y);
+}
@@ -0,0 +1,316 @@
+use crate::damage::{Damage, Vitals};
+use crate::geometry::{orientation, Orientation, Segment, EPSILON};
+use crate::graphics::renderable::mapdata::Map;
+use nalgebra_glm as ng;
+
+const EYE_HEIGHT: f64 = 0.9; // fraction of the height
+
+// Stays valid as long as the actor exists, never points to a later actor in the same slot
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ActorId {
+    index: u32,
+    generation: u32,
+}
+
+impl std::fmt::Display for ActorId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}.{}", self.index, self.generation)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ActorKind {
+    Player,
+    Decoration,
+    Monster,
+}
+
+// Result of moving along the floor from one point to another
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Step {
+    Inside, // still in the same sector
+    // Went into another sector through the edge, drop is how far the floor went down
+    Gate { sector: u32, edge: usize, drop: f32 },
+    Blocked, // by a wall or a gate which is too low
+}
+
+// The first edge of the sector which gets crossed going from one point to another, map coordinates
+pub fn crossed_edge(map: &Map, sector: u32, from: (f64, f64), to: (f64, f64)) -> Option<usize> {
+    let movement = Segment::new(from, to);
+
+    // Find the edge which is crossed first, walls win over gates when hit at the same spot (corners)
+    let current = &map.sectors[sector as usize];
+    let mut crossed: Option<(usize, f64, bool)> = None; // edge, t, is gate
+    for i in 0..current.corners.len() {
+        let corner0 = current.corners[i];
+        let corner1 = current.corners[(i + 1) % current.corners.len()];
+        let edge = Segment::new(
+            (corner0.0 as f64, corner0.1 as f64),
+            (corner1.0 as f64, corner1.1 as f64),
+        );
+
+        // Moving (back) into the sector is always allowed
+        if orientation(edge.a, edge.b, movement.b) == Orientation::CounterClockwise {
+            continue;
+        }
+
+        if let Some(hit) = movement.intersect(&edge) {
+            let is_gate = current.gates.iter().any(|gate| gate.own as usize == i);
+            let earlier = match crossed {
+                None => true,
+                Some((_, t, gate)) => {
+                    hit.t < t - EPSILON || (hit.t <= t + EPSILON && gate && !is_gate)
+                }
+            };
+            if earlier {
+                crossed = Some((i, hit.t, is_gate));
+            }
+        }
+    }
+
+    crossed.map(|(edge, _, _)| edge)
+}
+
+// Only looks at the first edge which gets crossed, map coordinates.
+// Everything moving through the map goes through here, so the player and other actors collide the same
+pub fn step(map: &Map, sector: u32, from: (f64, f64), to: (f64, f64), height: f32) -> Step {
+    let Some(edge) = crossed_edge(map, sector, from, to) else {
+        return Step::Inside;
+    };
+    let current = &map.sectors[sector as usize];
+    match current.gates.iter().find(|gate| gate.own as usize == edge) {
+        Some(gate) => {
+            let entering = &map.sectors[gate.target_sector as usize];
+            if entering.ceiling - entering.floor > height {
+                Step::Gate {
+                    sector: gate.target_sector,
+                    edge,
+                    drop: current.floor - entering.floor,
+                }
+            } else {
+                Step::Blocked
+            }
+        }
+        None => Step::Blocked,
+    }
+}
+
+// Anything besides the static sectors, everything in map coordinates
+#[derive(Clone, Debug)]
+pub struct Actor {
+    pub kind: ActorKind,
+    pub position: ng::DVec3, // of the feet
+    pub velocity: ng::DVec3, // units per second
+    pub angle: f64,          // degrees, see Thing
+    pub sector: u32,
+    pub radius: f64,
+    pub height: f32,
+    pub vitals: Vitals,
+    pub shootable: bool,        // blocks shots and takes damage
+    pub sprite: Option<String>, // None is not drawn
+}
+
+impl Actor {
+    pub fn new(
+        kind: ActorKind,
+        position: ng::DVec3,
+        sector: u32,
+        radius: f64,
+        height: f32,
+    ) -> Self {
+        Actor {
+            kind,
+            position,
+            velocity: ng::DVec3::zeros(),
+            angle: 0.0,
+            sector,
+            radius,
+            height,
+            vitals: Vitals::new(1),
+            shootable: false,
+            sprite: None,
+        }
+    }
+
+    pub fn dead(&self) -> bool {
+        self.vitals.health <= 0
+    }
+
+    pub fn eye(&self) -> ng::DVec3 {
+        self.position + ng::DVec3::new(0.0, self.height as f64 * EYE_HEIGHT, 0.0)
+    }
+
+    pub fn center(&self) -> ng::DVec3 {
+        self.position + ng::DVec3::new(0.0, self.height as f64 * 0.5, 0.0)
+    }
+
+    // Returns the health which got lost
+    pub fn damage(&mut self, damage: Damage) -> i32 {
+        if !self.shootable || self.dead() {
+            return 0;
+        }
+
+        let lost = self.vitals.damage(damage);
+        self.vitals.health = self.vitals.health.max(0);
+        lost
+    }
+
+    // Moves by (x, z) and stays on the floor, returns how it went.
+    // Only the center collides, the same as for the player. The radius is for shots and
+    // explosions, keeping it away from walls would need sliding along them which nothing does
+    pub fn walk(&mut self, delta: (f64, f64), map: &Map) -> Step {
+        let from = (self.position.x, self.position.z);
+        let to = (from.0 + delta.0, from.1 + delta.1);
+
+        let step = step(map, self.sector, from, to, self.height);
+        if step == Step::Blocked {
+            return step;
+        }
+        if let Step::Gate { sector, .. } = step {
+            self.sector = sector;
+        }
+        self.position.x = to.0;
+        self.position.z = to.1;
+        self.position.y = map.sectors[self.sector as usize].floor as f64;
+        step
+    }
+
+    // Distance along a normalized direction to the upright cylinder of the actor
+    pub fn ray_distance(
+        &self,
+        origin: ng::DVec3,
+        direction: ng::DVec3,
+        max_dist: f64,
+    ) -> Option<f64> {
+        let offset = (origin.x - self.position.x, origin.z - self.position.z);
+        let a = direction.x * direction.x + direction.z * direction.z;
+        if a <= EPSILON {
+            return None;
+        }
+        let b = 2.0 * (offset.0 * direction.x + offset.1 * direction.z);
+        let c = offset.0 * offset.0 + offset.1 * offset.1 - self.radius * self.radius;
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+
+        let t = ((-b - discriminant.sqrt()) / (2.0 * a)).max(0.0);
+        let y = origin.y + direction.y * t;
+        let bottom = self.position.y;
+        if t > max_dist || y < bottom || y > bottom + self.height as f64 {
+            return None;
+        }
+        Some(t)
+    }
+}
+
+struct Slot {
+    generation: u32,
+    actor: Option<Actor>,
+}
+
+// Storage for all actors, slots of removed actors get reused
+#[derive(Default)]
+pub struct Actors {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+}
+
+impl Actors {
+    pub fn spawn(&mut self, actor: Actor) -> ActorId {
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.generation += 1;
+                slot.actor = Some(actor);
+                ActorId {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    actor: Some(actor),
+                });
+                ActorId {
+                    index: self.slots.len() as u32 - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: ActorId) -> Option<Actor> {
+        let slot = self.slots.get_mut(id.index as usize)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        let actor = slot.actor.take()?;
+        self.free.push(id.index);
+        Some(actor)
+    }
+
+    pub fn get(&self, id: ActorId) -> Option<&Actor> {
+        self.slots
+            .get(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.actor.as_ref())
+    }
+
+    pub fn get_mut(&mut self, id: ActorId) -> Option<&mut Actor> {
+        self.slots
+            .get_mut(id.index as usize)
+            .filter(|slot| slot.generation == id.generation)
+            .and_then(|slot| slot.actor.as_mut())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ActorId, &Actor)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            let id = ActorId {
+                index: index as u32,
+                generation: slot.generation,
+            };
+            slot.actor.as_ref().map(|actor| (id, actor))
+        })
+    }
+
+    pub fn in_sector(&self, sector: u32) -> impl Iterator<Item = (ActorId, &Actor)> {
+        self.iter().filter(move |(_, actor)| actor.sector == sector)
+    }
+
+    // Moves everything by its velocity, running into a wall stops.
+    // Everything stays on the floor, moving floors carry it along. The player moves itself
+    pub fn update(&mut self, delta_time: f64, map: &Map) {
+        let actors = self.slots.iter_mut().filter_map(|slot| slot.actor.as_mut());
+        for actor in actors.filter(|actor| actor.kind != ActorKind::Player) {
+            actor.position.y = map.sectors[actor.sector as usize].floor as f64;
+            if actor.velocity == ng::DVec3::zeros() {
+                continue;
+            }
+
+            let delta = (actor.velocity.x * delta_time, actor.velocity.z * delta_time);
+            if actor.walk(delta, map) == Step::Blocked {
+                actor.velocity = ng::DVec3::zeros();
+            }
+        }
+    }
+
+    // The first shootable actor on a ray besides the one shooting, direction is normalized
+    pub fn raycast(
+        &self,
+        origin: ng::DVec3,
+        direction: ng::DVec3,
+        max_dist: f64,
+        ignore: Option<ActorId>,
+    ) -> Option<(ActorId, f64)> {
+        self.iter()
+            .filter(|(id, actor)| actor.shootable && !actor.dead() && Some(*id) != ignore)
+            .filter_map(|(id, actor)| {
+                actor
+                    .ray_distance(origin, direction, max_dist)
+                    .map(|distance| (id, distance))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+}
@@ -0,0 +1,205 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DamageType {
+    Hitscan,
+    Explosion,
+    Environmental,
+    Fall,
+}
+
+impl DamageType {
+    pub const ALL: [DamageType; 4] = [
+        DamageType::Hitscan,
+        DamageType::Explosion,
+        DamageType::Environmental,
+        DamageType::Fall,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DamageType::Hitscan => "hitscan",
+            DamageType::Explosion => "explosion",
+            DamageType::Environmental => "environmental",
+            DamageType::Fall => "fall",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<DamageType> {
+        DamageType::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    // Armor does not help against floors and falling
+    fn armored(&self) -> bool {
+        matches!(self, DamageType::Hitscan | DamageType::Explosion)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Damage {
+    pub amount: i32,
+    pub kind: DamageType,
+}
+
+impl Damage {
+    pub fn new(amount: i32, kind: DamageType) -> Self {
+        Damage { amount, kind }
+    }
+}
+
+// Factor of the damage which gets through, 1.0 = no resistance, 0.0 = immune
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Resistances {
+    pub hitscan: f64,
+    pub explosion: f64,
+    pub environmental: f64,
+    pub fall: f64,
+}
+
+impl Default for Resistances {
+    fn default() -> Self {
+        Resistances {
+            hitscan: 1.0,
+            explosion: 1.0,
+            environmental: 1.0,
+            fall: 1.0,
+        }
+    }
+}
+
+impl Resistances {
+    // Factors by damage type name, the missing ones stay at 1.0
+    pub fn parse(json: &json::JsonValue) -> Option<Self> {
+        let mut resistances = Resistances::default();
+        for (name, factor) in json.entries() {
+            let factor = factor.as_f64().filter(|factor| *factor >= 0.0)?;
+            match DamageType::from_name(name)? {
+                DamageType::Hitscan => resistances.hitscan = factor,
+                DamageType::Explosion => resistances.explosion = factor,
+                DamageType::Environmental => resistances.environmental = factor,
+                DamageType::Fall => resistances.fall = factor,
+            }
+        }
+        Some(resistances)
+    }
+
+    pub fn factor(&self, kind: DamageType) -> f64 {
+        match kind {
+            DamageType::Hitscan => self.hitscan,
+            DamageType::Explosion => self.explosion,
+            DamageType::Environmental => self.environmental,
+            DamageType::Fall => self.fall,
+        }
+    }
+}
+
+// Health and armor of anything that can be damaged, the player as well as enemies
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Vitals {
+    pub health: i32,
+    pub armor: i32,
+    pub absorption: f64, // fraction of the damage the armor takes
+    pub resistances: Resistances,
+}
+
+impl Vitals {
+    pub fn new(health: i32) -> Self {
+        Vitals {
+            health,
+            armor: 0,
+            absorption: 0.0,
+            resistances: Resistances::default(),
+        }
+    }
+
+    // Resistances first, then the armor absorbs its part as long as there is some left.
+    // Returns the health which got lost
+    pub fn damage(&mut self, damage: Damage) -> i32 {
+        let amount = (damage.amount as f64 * self.resistances.factor(damage.kind)).round() as i32;
+        if amount <= 0 {
+            return 0;
+        }
+
+        let absorbed = if damage.kind.armored() {
+            ((amount as f64 * self.absorption).round() as i32).min(self.armor)
+        } else {
+            0
+        };
+        self.armor -= absorbed;
+
+        let lost = amount - absorbed;
+        self.health -= lost;
+        lost
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn armored(armor: i32, absorption: f64) -> Vitals {
+        Vitals {
+            armor,
+            absorption,
+            ..Vitals::new(100)
+        }
+    }
+
+    #[test]
+    fn resistance_before_armor() {
+        let mut vitals = armored(100, 0.5);
+        vitals.resistances.hitscan = 0.5;
+        // 20 halved to 10, the armor takes 5 of them
+        assert_eq!(vitals.damage(Damage::new(20, DamageType::Hitscan)), 5);
+        assert_eq!((vitals.health, vitals.armor), (95, 95));
+    }
+
+    #[test]
+    fn absorption_capped_by_armor() {
+        let mut vitals = armored(3, 0.5);
+        assert_eq!(vitals.damage(Damage::new(20, DamageType::Explosion)), 17);
+        assert_eq!((vitals.health, vitals.armor), (83, 0));
+
+        assert_eq!(vitals.damage(Damage::new(20, DamageType::Explosion)), 20);
+        assert_eq!(vitals.health, 63);
+    }
+
+    #[test]
+    fn unarmored_types() {
+        let mut vitals = armored(100, 1.0);
+        assert_eq!(vitals.damage(Damage::new(10, DamageType::Fall)), 10);
+        assert_eq!(
+            vitals.damage(Damage::new(10, DamageType::Environmental)),
+            10
+        );
+        assert_eq!((vitals.health, vitals.armor), (80, 100));
+    }
+
+    #[test]
+    fn rounded_to_nothing() {
+        let mut vitals = armored(100, 0.5);
+        vitals.resistances.explosion = 0.1;
+        assert_eq!(vitals.damage(Damage::new(4, DamageType::Explosion)), 0);
+        assert_eq!((vitals.health, vitals.armor), (100, 100));
+
+        vitals.resistances.explosion = 0.0;
+        assert_eq!(vitals.damage(Damage::new(100, DamageType::Explosion)), 0);
+        assert_eq!((vitals.health, vitals.armor), (100, 100));
+    }
+
+    #[test]
+    fn parse_resistances() {
+        let resistances = Resistances::parse(&json::parse(r#"{ "explosion": 0.5 }"#).unwrap());
+        assert_eq!(
+            resistances,
+            Some(Resistances {
+                explosion: 0.5,
+                ..Resistances::default()
+            })
+        );
+        assert_eq!(
+            Resistances::parse(&json::JsonValue::Null),
+            Some(Resistances::default())
+        );
+        assert!(Resistances::parse(&json::parse(r#"{ "fire": 0.5 }"#).unwrap()).is_none());
+        assert!(Resistances::parse(&json::parse(r#"{ "fall": -1.0 }"#).unwrap()).is_none());
+    }
+}
@@ -0,0 +1,249 @@
+use crate::input::TickCommand;
+use crate::things::Difficulty;
+use crate::world::WorldState;
+
+pub const EXTENSION: &str = "mooddemo";
+const VERSION: u32 = 2;
+
+// Positions may differ by float noise after a round trip through the file
+const TOLERANCE: f64 = 1e-6;
+
+// What the player looked like after a tick, used to detect desyncs on playback
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TickState {
+    pub pos: (f64, f64, f64),
+    pub sector: u32,
+}
+
+impl From<WorldState> for TickState {
+    fn from(state: WorldState) -> Self {
+        TickState {
+            pos: state.position,
+            sector: state.sector,
+        }
+    }
+}
+
+impl TickState {
+    fn matches(&self, other: &TickState) -> bool {
+        self.sector == other.sector
+            && (self.pos.0 - other.pos.0).abs() <= TOLERANCE
+            && (self.pos.1 - other.pos.1).abs() <= TOLERANCE
+            && (self.pos.2 - other.pos.2).abs() <= TOLERANCE
+    }
+}
+
+pub struct Demo {
+    pub map: String,
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub tick_rate: f64,
+    pub commands: Vec<TickCommand>,
+    pub states: Vec<TickState>,
+}
+
+impl Demo {
+    pub fn new(map: &str, seed: u64, difficulty: Difficulty) -> Self {
+        Demo {
+            map: map.to_string(),
+            seed,
+            difficulty,
+            tick_rate: crate::timing::TICK_RATE,
+            commands: Vec::new(),
+            states: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, cmd: &TickCommand, state: TickState) {
+        self.commands.push(*cmd);
+        self.states.push(state);
+    }
+
+    pub fn save(&self, path: &str) {
+        match std::fs::write(path, self.to_json().dump()) {
+            Ok(_) => println!("Saved demo '{path}' ({} ticks)", self.commands.len()),
+            Err(err) => println!("Could not save demo '{path}': {err}"),
+        }
+    }
+
+    pub fn load(path: &str) -> Option<Self> {
+        let input = std::fs::read_to_string(path).ok()?;
+        let demo = Demo::from_json(&json::parse(&input).ok()?);
+        if demo.is_none() {
+            println!("Demo '{path}' is invalid or has an unsupported version");
+        }
+        demo
+    }
+
+    fn to_json(&self) -> json::JsonValue {
+        let mut ticks = json::JsonValue::new_array();
+        for (cmd, state) in self.commands.iter().zip(&self.states) {
+            ticks
+                .push(json::array![
+                    cmd.forward,
+                    cmd.right,
+                    cmd.yaw,
+                    cmd.pitch,
+                    cmd.held,
+                    cmd.pressed,
+                    state.pos.0,
+                    state.pos.1,
+                    state.pos.2,
+                    state.sector
+                ])
+                .unwrap();
+        }
+
+        json::object! {
+            "version": VERSION,
+            "map": self.map.as_str(),
+            "seed": self.seed,
+            "difficulty": self.difficulty.name(),
+            "tickRate": self.tick_rate,
+            "ticks": ticks,
+        }
+    }
+
+    fn from_json(json: &json::JsonValue) -> Option<Self> {
+        if json["version"].as_u32() != Some(VERSION) {
+            return None;
+        }
+
+        // Older demos were all played on normal
+        let difficulty = match json["difficulty"].as_str() {
+            Some(name) => Difficulty::from_name(name)?,
+            None => Difficulty::Normal,
+        };
+        let mut demo = Demo::new(json["map"].as_str()?, json["seed"].as_u64()?, difficulty);
+        demo.tick_rate = json["tickRate"].as_f64()?;
+        for tick in json["ticks"].members() {
+            demo.commands.push(TickCommand {
+                forward: tick[0].as_f64()?,
+                right: tick[1].as_f64()?,
+                yaw: tick[2].as_f64()?,
+                pitch: tick[3].as_f64()?,
+                held: tick[4].as_u32()?,
+                pressed: tick[5].as_u32()?,
+            });
+            demo.states.push(TickState {
+                pos: (tick[6].as_f64()?, tick[7].as_f64()?, tick[8].as_f64()?),
+                sector: tick[9].as_u32()?,
+            });
+        }
+
+        Some(demo)
+    }
+}
+
+pub struct Playback {
+    demo: Demo,
+    tick: usize,
+    desync: Option<usize>, // first tick which did not match the recording
+}
+
+impl Playback {
+    pub fn new(demo: Demo) -> Self {
+        Playback {
+            demo,
+            tick: 0,
+            desync: None,
+        }
+    }
+
+    pub fn demo(&self) -> &Demo {
+        &self.demo
+    }
+
+    pub fn next_command(&mut self) -> Option<TickCommand> {
+        let cmd = self.demo.commands.get(self.tick).copied();
+        if cmd.is_some() {
+            self.tick += 1;
+        }
+        cmd
+    }
+
+    // Compares the state after the last played tick with the recording
+    pub fn check(&mut self, state: TickState) -> bool {
+        let Some(expected) = self
+            .tick
+            .checked_sub(1)
+            .and_then(|i| self.demo.states.get(i))
+        else {
+            return true;
+        };
+
+        if expected.matches(&state) {
+            return true;
+        }
+
+        if self.desync.is_none() {
+            self.desync = Some(self.tick - 1);
+            println!(
+                "Demo desync at tick {}: expected {:?}, got {:?}",
+                self.tick - 1,
+                expected,
+                state
+            );
+        }
+        false
+    }
+
+    pub fn finished(&self) -> bool {
+        self.tick >= self.demo.commands.len()
+    }
+
+    pub fn desync(&self) -> Option<usize> {
+        self.desync
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::{self, parse_script, Source};
+    use crate::world::World;
+
+    const MAP: &str = "test2.json";
+    const SEED: u64 = 7;
+
+    #[test]
+    fn playback_after_round_trip() {
+        let script = "forward 9\nturn 90\nforward 40\nturn -30\nbackward 10\nleft 5\n";
+        let commands = parse_script(script).unwrap();
+
+        let mut world = World::load(MAP, SEED, Difficulty::Easy);
+        let mut demo = Demo::new(MAP, SEED, Difficulty::Easy);
+        for cmd in &commands {
+            world.tick(cmd);
+            demo.record(cmd, world.state().into());
+        }
+        let recorded = world.state();
+        assert_eq!(recorded.tick, commands.len() as u64);
+        // Walked away from the spawn at 3, 3
+        let (x, _, z) = recorded.position;
+        assert!((x - 3.0).abs() + (-z - 3.0).abs() > 1.0);
+
+        let text = demo.to_json().dump();
+        let loaded = Demo::from_json(&json::parse(&text).unwrap()).unwrap();
+        assert_eq!(loaded.map, MAP);
+        assert_eq!(loaded.difficulty, Difficulty::Easy);
+        assert_eq!(loaded.commands, demo.commands);
+
+        let mut world = World::load(&loaded.map, loaded.seed, loaded.difficulty);
+        let played = headless::run(&mut world, Source::Demo(Playback::new(loaded)), false);
+        assert_eq!(played.tick, recorded.tick);
+        assert_eq!(played.sector, recorded.sector);
+        assert_eq!(played.health, recorded.health);
+        assert!(TickState::from(played).matches(&recorded.into()));
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut json = Demo::new(MAP, SEED, Difficulty::Normal).to_json();
+        json["version"] = (VERSION + 1).into();
+        assert!(Demo::from_json(&json).is_none());
+        // Recorded before the difficulty was stored
+        json["version"] = 1.into();
+        assert!(Demo::from_json(&json).is_none());
+    }
+}
@@ -0,0 +1,187 @@
+use crate::graphics::renderable::mapdata::Map;
+use crate::inventory::Inventory;
+
+const SPEED: f32 = 2.0; // units per second
+const WAIT: f64 = 3.0; // seconds open before closing again
+
+// A sector whose ceiling moves between its floor and the open height
+#[derive(Clone, Debug)]
+pub struct DoorDef {
+    pub open: f32,  // ceiling when open
+    pub speed: f32, // units per second
+    pub wait: f64,
+    pub stay_open: bool,
+    pub key: Option<String>, // needed to open it
+}
+
+impl DoorDef {
+    pub fn parse(json: &json::JsonValue, open: f32) -> Option<Self> {
+        let speed = json["speed"].as_f32().unwrap_or(SPEED);
+        if speed <= 0.0 {
+            return None;
+        }
+
+        Some(DoorDef {
+            open,
+            speed,
+            wait: json["wait"].as_f64().unwrap_or(WAIT),
+            stay_open: json["stayOpen"].as_bool().unwrap_or(false),
+            key: json["key"].as_str().map(|key| key.to_string()),
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DoorState {
+    Closed,
+    Opening,
+    Open,
+    Closing,
+}
+
+impl DoorState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DoorState::Closed => "closed",
+            DoorState::Opening => "opening",
+            DoorState::Open => "open",
+            DoorState::Closing => "closing",
+        }
+    }
+}
+
+// What using a door did
+#[derive(Clone, PartialEq, Debug)]
+pub enum Activation {
+    Moved,
+    Busy, // already opening
+    Locked(String),
+}
+
+struct Door {
+    sector: u32,
+    state: DoorState,
+    wait: f64, // seconds left until closing
+}
+
+// All doors of a map, they start closed
+pub struct Doors {
+    doors: Vec<Door>,
+}
+
+impl Doors {
+    pub fn new(map: &Map) -> Self {
+        let doors = map
+            .sectors
+            .iter()
+            .enumerate()
+            .filter(|(_, sector)| sector.door.is_some())
+            .map(|(index, _)| Door {
+                sector: index as u32,
+                state: DoorState::Closed,
+                wait: 0.0,
+            })
+            .collect();
+        Doors { doors }
+    }
+
+    pub fn state(&self, sector: u32) -> Option<DoorState> {
+        self.doors
+            .iter()
+            .find(|door| door.sector == sector)
+            .map(|door| door.state)
+    }
+
+    // Opens closed doors and closes open ones, the key has to be in the inventory for locked ones
+    pub fn activate(
+        &mut self,
+        map: &Map,
+        sector: u32,
+        inventory: &Inventory,
+    ) -> Option<Activation> {
+        let door = self.doors.iter_mut().find(|door| door.sector == sector)?;
+        let def = map.sectors[sector as usize].door.as_ref()?;
+
+        if let Some(key) = &def.key {
+            if !inventory.has_key(key) {
+                return Some(Activation::Locked(key.clone()));
+            }
+        }
+
+        door.state = match door.state {
+            DoorState::Closed | DoorState::Closing => DoorState::Opening,
+            DoorState::Open => DoorState::Closing,
+            DoorState::Opening => return Some(Activation::Busy),
+        };
+        Some(Activation::Moved)
+    }
+
+    // Opens the door from afar like a switch, no key needed. Returns false if it is open or opening
+    pub fn open(&mut self, sector: u32) -> bool {
+        let Some(door) = self.doors.iter_mut().find(|door| door.sector == sector) else {
+            return false;
+        };
+        if !matches!(door.state, DoorState::Closed | DoorState::Closing) {
+            return false;
+        }
+        door.state = DoorState::Opening;
+        true
+    }
+
+    // Occupants are the sectors and heights of everything a closing door can not go through,
+    // it opens again instead. Returns the doors which changed their state
+    pub fn update(
+        &mut self,
+        map: &mut Map,
+        occupants: &[(u32, f32)],
+        delta_time: f64,
+    ) -> Vec<(u32, DoorState)> {
+        let mut changed = Vec::new();
+        for door in &mut self.doors {
+            let sector = &map.sectors[door.sector as usize];
+            let Some(def) = sector.door.clone() else {
+                continue;
+            };
+            let (floor, ceiling) = (sector.floor, sector.ceiling);
+            let step = def.speed * delta_time as f32;
+
+            let before = door.state;
+            match door.state {
+                DoorState::Closed => {}
+                DoorState::Opening => {
+                    let open = def.open;
+                    map.set_heights(door.sector, floor, (ceiling + step).min(open));
+                    if ceiling + step >= open {
+                        door.state = DoorState::Open;
+                        door.wait = def.wait;
+                    }
+                }
+                DoorState::Open => {
+                    door.wait -= delta_time;
+                    if door.wait <= 0.0 && !def.stay_open {
+                        door.state = DoorState::Closing;
+                    }
+                }
+                DoorState::Closing => {
+                    let lowered = (ceiling - step).max(floor);
+                    let blocked = occupants
+                        .iter()
+                        .any(|(inside, height)| *inside == door.sector && floor + height > lowered);
+                    if blocked {
+                        door.state = DoorState::Opening;
+                    } else {
+                        map.set_heights(door.sector, floor, lowered);
+                        if lowered <= floor {
+                            door.state = DoorState::Closed;
+                        }
+                    }
+                }
+            }
+
+            if door.state != before {
+                changed.push((door.sector, door.state));
+            }
+        }
+        changed
+    }
+}
@@ -0,0 +1,539 @@
+use crate::actors::{Actor, ActorId, Step};
+use crate::damage::{Damage, DamageType, Resistances};
+use crate::graphics::renderable::mapdata::Map;
+use crate::navigation::{Clearance, Pathfinder};
+use crate::weapons::ProjectileDef;
+use nalgebra_glm as ng;
+use std::collections::HashMap;
+
+pub const CONFIG_PATH: &str = "assets/config/enemies.json";
+
+const MAX_STEP: f32 = 0.5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum EnemyState {
+    Idle,
+    See, // just noticed the target
+    Chase,
+    Attack,
+    Pain,
+    Death,
+}
+
+impl EnemyState {
+    pub const ALL: [EnemyState; 6] = [
+        EnemyState::Idle,
+        EnemyState::See,
+        EnemyState::Chase,
+        EnemyState::Attack,
+        EnemyState::Pain,
+        EnemyState::Death,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EnemyState::Idle => "idle",
+            EnemyState::See => "see",
+            EnemyState::Chase => "chase",
+            EnemyState::Attack => "attack",
+            EnemyState::Pain => "pain",
+            EnemyState::Death => "death",
+        }
+    }
+
+    // Idle and chase loop, everything else plays once
+    fn looping(&self) -> bool {
+        matches!(self, EnemyState::Idle | EnemyState::Chase)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Animation {
+    pub frames: Vec<String>, // sprites
+    pub frame_time: f64,     // seconds per frame
+}
+
+impl Animation {
+    fn parse(json: &json::JsonValue) -> Option<Self> {
+        let frames: Vec<String> = json["frames"]
+            .members()
+            .filter_map(|frame| frame.as_str().map(|frame| frame.to_string()))
+            .collect();
+        if frames.is_empty() {
+            return None;
+        }
+
+        Some(Animation {
+            frames,
+            frame_time: json["time"].as_f64()?,
+        })
+    }
+
+    fn duration(&self) -> f64 {
+        self.frames.len() as f64 * self.frame_time
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum AttackKind {
+    Melee,
+    Hitscan { spread: f64 }, // degrees
+    Projectile(ProjectileDef),
+}
+
+#[derive(Clone, Debug)]
+pub struct AttackDef {
+    pub kind: AttackKind,
+    pub damage: Damage,
+    pub range: f64,
+    pub cooldown: f64, // seconds between attacks
+}
+
+impl AttackDef {
+    fn parse(json: &json::JsonValue) -> Option<Self> {
+        let kind = match json["type"].as_str()? {
+            "melee" => AttackKind::Melee,
+            "hitscan" => AttackKind::Hitscan {
+                spread: json["spread"].as_f64().unwrap_or(0.0),
+            },
+            "projectile" => AttackKind::Projectile(ProjectileDef::parse(&json["projectile"])?),
+            _ => return None,
+        };
+
+        Some(AttackDef {
+            kind,
+            damage: Damage::new(
+                json["damage"].as_i32()?,
+                DamageType::from_name(json["damageType"].as_str()?)?,
+            ),
+            range: json["range"].as_f64()?,
+            cooldown: json["cooldown"].as_f64()?,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EnemyDef {
+    pub health: i32,
+    pub speed: f64, // units per second
+    pub radius: f64,
+    pub height: f32,
+    pub max_step: f32,    // highest step it can climb
+    pub sight: f64,       // how far it can see
+    pub pain_chance: f64, // of flinching when hurt
+    pub resistances: Resistances,
+    pub attack: AttackDef,
+    animations: HashMap<EnemyState, Animation>,
+}
+
+impl EnemyDef {
+    fn parse(json: &json::JsonValue) -> Option<Self> {
+        let mut animations = HashMap::new();
+        for state in EnemyState::ALL {
+            let animation = &json["states"][state.name()];
+            if !animation.is_null() {
+                animations.insert(state, Animation::parse(animation)?);
+            }
+        }
+
+        Some(EnemyDef {
+            health: json["health"].as_i32()?,
+            speed: json["speed"].as_f64()?,
+            radius: json["radius"].as_f64()?,
+            height: json["height"].as_f32()?,
+            max_step: json["maxStep"].as_f32().unwrap_or(MAX_STEP),
+            sight: json["sight"].as_f64()?,
+            pain_chance: json["painChance"].as_f64().unwrap_or(0.0),
+            resistances: Resistances::parse(&json["resistances"])?,
+            attack: AttackDef::parse(&json["attack"])?,
+            animations,
+        })
+    }
+}
+
+// All enemy types by name
+pub fn load_enemies(path: &str) -> HashMap<String, EnemyDef> {
+    let input = std::fs::read_to_string(path).expect("Could not find enemy list");
+    let config = json::parse(&input).expect("Could not parse enemy list");
+
+    let mut enemies = HashMap::new();
+    for (name, enemy) in config.entries() {
+        match EnemyDef::parse(enemy) {
+            Some(def) => {
+                enemies.insert(name.to_string(), def);
+            }
+            None => println!("Invalid enemy '{name}' in '{path}'"),
+        }
+    }
+    enemies
+}
+
+// What the enemies go after, map coordinates
+pub struct Target {
+    pub eye: ng::DVec3,
+    pub alive: bool,
+}
+
+// The brain of an actor
+pub struct Enemy {
+    pub kind: String,
+    pub actor: ActorId,
+    pub deaf: bool, // only wakes up by seeing the target or getting hurt
+    state: EnemyState,
+    time: f64,     // seconds in the current state
+    cooldown: f64, // seconds until the next attack
+}
+
+impl Enemy {
+    pub fn new(kind: &str, actor: ActorId) -> Self {
+        Enemy {
+            kind: kind.to_string(),
+            actor,
+            deaf: false,
+            state: EnemyState::Idle,
+            time: 0.0,
+            cooldown: 0.0,
+        }
+    }
+
+    pub fn state(&self) -> EnemyState {
+        self.state
+    }
+
+    fn set_state(&mut self, state: EnemyState) {
+        self.state = state;
+        self.time = 0.0;
+    }
+
+    // Returns true if it woke up
+    pub fn hear(&mut self) -> bool {
+        if self.deaf || self.state != EnemyState::Idle {
+            return false;
+        }
+        self.set_state(EnemyState::See);
+        true
+    }
+
+    // Roll decides about flinching, 0..1
+    pub fn hurt(&mut self, def: &EnemyDef, dead: bool, roll: f64) {
+        if self.state == EnemyState::Death {
+            return;
+        }
+
+        if dead {
+            self.set_state(EnemyState::Death);
+        } else if roll < def.pain_chance {
+            self.set_state(EnemyState::Pain);
+        } else if self.state == EnemyState::Idle {
+            self.set_state(EnemyState::See);
+        }
+    }
+
+    // Returns true if the attack happens during this tick
+    pub fn update(
+        &mut self,
+        def: &EnemyDef,
+        actor: &mut Actor,
+        target: &Target,
+        map: &Map,
+        paths: &mut Pathfinder,
+        delta_time: f64,
+    ) -> bool {
+        let before = self.time;
+        self.time += delta_time;
+        self.cooldown = (self.cooldown - delta_time).max(0.0);
+        let finished = def
+            .animations
+            .get(&self.state)
+            .is_none_or(|animation| self.time >= animation.duration());
+
+        let to_target = (
+            target.eye.x - actor.position.x,
+            target.eye.z - actor.position.z,
+        );
+        let distance = (to_target.0 * to_target.0 + to_target.1 * to_target.1).sqrt();
+
+        let mut attack = false;
+        match self.state {
+            EnemyState::Death => {}
+            EnemyState::Idle => {
+                if target.alive && distance <= def.sight && sees(actor, target, map) {
+                    self.set_state(EnemyState::See);
+                }
+            }
+            EnemyState::See | EnemyState::Pain => {
+                if finished {
+                    self.set_state(EnemyState::Chase);
+                }
+            }
+            EnemyState::Chase => {
+                if !target.alive {
+                    self.set_state(EnemyState::Idle);
+                } else if distance <= def.attack.range
+                    && self.cooldown <= 0.0
+                    && sees(actor, target, map)
+                {
+                    self.set_state(EnemyState::Attack);
+                    self.cooldown = def.attack.cooldown;
+                    face(actor, to_target);
+                } else if distance > def.radius {
+                    chase(def, actor, target, map, paths, delta_time);
+                }
+            }
+            EnemyState::Attack => {
+                // The attack happens when the last frame comes up
+                let fire_time = def
+                    .animations
+                    .get(&EnemyState::Attack)
+                    .map_or(0.0, |animation| animation.duration() - animation.frame_time);
+                attack = before <= fire_time && self.time > fire_time;
+                if finished {
+                    self.set_state(EnemyState::Chase);
+                }
+            }
+        }
+
+        actor.sprite = def.animations.get(&self.state).map(|animation| {
+            let frame = (self.time / animation.frame_time) as usize;
+            let frame = if self.state.looping() {
+                frame % animation.frames.len()
+            } else {
+                frame.min(animation.frames.len() - 1)
+            };
+            animation.frames[frame].clone()
+        });
+        attack
+    }
+}
+
+fn sees(actor: &Actor, target: &Target, map: &Map) -> bool {
+    map.line_of_sight(actor.eye(), target.eye).visible
+}
+
+// Map angles, 0 looks along x and 90 along z
+fn face(actor: &mut Actor, direction: (f64, f64)) {
+    actor.angle = direction.1.atan2(direction.0).to_degrees();
+}
+
+// Follows the path to the target, slides along walls by trying the axes on their own
+// when the direct way is blocked
+fn chase(
+    def: &EnemyDef,
+    actor: &mut Actor,
+    target: &Target,
+    map: &Map,
+    paths: &mut Pathfinder,
+    delta_time: f64,
+) {
+    let clearance = Clearance {
+        height: def.height,
+        max_step: def.max_step,
+        radius: def.radius,
+    };
+    let from = (actor.position.x, actor.position.z);
+    let Some(path) = paths.find(map, from, (target.eye.x, target.eye.z), clearance) else {
+        return;
+    };
+
+    let waypoint = path[0];
+    let to_waypoint = (waypoint.0 - from.0, waypoint.1 - from.1);
+    let distance = (to_waypoint.0 * to_waypoint.0 + to_waypoint.1 * to_waypoint.1).sqrt();
+    if distance <= 0.0 {
+        return;
+    }
+    let speed = (def.speed * delta_time).min(distance) / distance;
+    let delta = (to_waypoint.0 * speed, to_waypoint.1 * speed);
+
+    face(actor, delta);
+    for delta in [delta, (delta.0, 0.0), (0.0, delta.1)] {
+        if actor.walk(delta, map) != Step::Blocked {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actors::{ActorKind, Actors};
+    use crate::graphics::renderable::mapdata::tests::rooms;
+    use crate::timing::TICK_TIME;
+
+    fn def() -> EnemyDef {
+        EnemyDef::parse(
+            &json::parse(
+                r#"{ "health": 3, "speed": 1.0, "radius": 0.2, "height": 0.75, "sight": 10.0,
+                     "painChance": 0.5,
+                     "attack": { "type": "melee", "damage": 1, "damageType": "hitscan",
+                                 "range": 1.0, "cooldown": 1.0 },
+                     "states": {
+                         "idle": { "frames": ["IDLE"], "time": 0.5 },
+                         "see": { "frames": ["SEE"], "time": 0.1 },
+                         "chase": { "frames": ["CHASE1", "CHASE2"], "time": 0.1 },
+                         "attack": { "frames": ["ATTACK1", "ATTACK2"], "time": 0.1 },
+                         "pain": { "frames": ["PAIN"], "time": 0.1 },
+                         "death": { "frames": ["DEATH1", "DEATH2"], "time": 0.1 }
+                     } }"#,
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    }
+
+    // An enemy in the high room of the rooms and the target it goes after
+    struct Scene {
+        map: Map,
+        def: EnemyDef,
+        actors: Actors,
+        paths: Pathfinder,
+        enemy: Enemy,
+        target: Target,
+    }
+
+    impl Scene {
+        fn new(x: f64, target_x: f64) -> Self {
+            let def = def();
+            let mut actors = Actors::default();
+            let id = actors.spawn(Actor::new(
+                ActorKind::Monster,
+                ng::DVec3::new(x, 0.0, 1.0),
+                0,
+                def.radius,
+                def.height,
+            ));
+            Scene {
+                map: rooms(),
+                def,
+                actors,
+                paths: Pathfinder::default(),
+                enemy: Enemy::new("test", id),
+                target: Target {
+                    eye: ng::DVec3::new(target_x, 0.6, 1.0),
+                    alive: true,
+                },
+            }
+        }
+
+        fn actor(&self) -> &Actor {
+            self.actors.get(self.enemy.actor).unwrap()
+        }
+
+        fn tick(&mut self) -> bool {
+            let actor = self.actors.get_mut(self.enemy.actor).unwrap();
+            let (def, target, map) = (&self.def, &self.target, &self.map);
+            self.enemy
+                .update(def, actor, target, map, &mut self.paths, TICK_TIME)
+        }
+
+        // Ticks until the state changes, returns the ticks it took and the attack ticks
+        fn run(&mut self) -> (u32, Vec<u32>) {
+            let state = self.enemy.state();
+            let mut attacks = Vec::new();
+            for tick in 1..=100 {
+                if self.tick() {
+                    attacks.push(tick);
+                }
+                if self.enemy.state() != state {
+                    return (tick, attacks);
+                }
+            }
+            panic!("Stuck in {}", state.name());
+        }
+    }
+
+    #[test]
+    fn wake_chase_attack() {
+        let mut scene = Scene::new(0.5, 1.8);
+        assert_eq!(scene.enemy.state(), EnemyState::Idle);
+        assert_eq!(scene.run(), (1, vec![]));
+        assert_eq!(scene.enemy.state(), EnemyState::See);
+        assert_eq!(scene.actor().sprite.as_deref(), Some("SEE"));
+
+        // 0.1 seconds of seeing
+        assert_eq!(scene.run(), (4, vec![]));
+        assert_eq!(scene.enemy.state(), EnemyState::Chase);
+
+        // Closes in up to the attack range
+        let start = scene.actor().position.x;
+        scene.run();
+        assert_eq!(scene.enemy.state(), EnemyState::Attack);
+        let moved = scene.actor().position.x - start;
+        assert!(moved > 0.25 && moved < 0.35);
+        assert_eq!(scene.actor().angle, 0.0);
+    }
+
+    // The attack fires once, when the last frame comes up
+    #[test]
+    fn attack_on_last_frame() {
+        let mut scene = Scene::new(1.0, 1.8);
+        scene.enemy.set_state(EnemyState::Chase);
+        assert_eq!(scene.run(), (1, vec![]));
+        assert_eq!(scene.enemy.state(), EnemyState::Attack);
+
+        let fire_tick = (0.1 / TICK_TIME).ceil() as u32;
+        let mut attacks = Vec::new();
+        for tick in 1..=fire_tick + 1 {
+            if scene.tick() {
+                attacks.push(tick);
+                assert_eq!(scene.actor().sprite.as_deref(), Some("ATTACK2"));
+            } else if tick < fire_tick {
+                assert_eq!(scene.actor().sprite.as_deref(), Some("ATTACK1"));
+            }
+        }
+        assert_eq!(attacks, vec![fire_tick]);
+
+        // Back to chasing, the cooldown keeps it from attacking right away
+        scene.run();
+        assert_eq!(scene.enemy.state(), EnemyState::Chase);
+        for _ in 0..10 {
+            assert!(!scene.tick());
+            assert_eq!(scene.enemy.state(), EnemyState::Chase);
+        }
+    }
+
+    #[test]
+    fn pain_and_death() {
+        let mut scene = Scene::new(0.5, 1.8);
+        let def = def();
+
+        // Flinching depends on the roll, getting hurt wakes it up either way
+        scene.enemy.hurt(&def, false, 0.9);
+        assert_eq!(scene.enemy.state(), EnemyState::See);
+        scene.enemy.hurt(&def, false, 0.1);
+        assert_eq!(scene.enemy.state(), EnemyState::Pain);
+        assert_eq!(scene.run(), (4, vec![]));
+        assert_eq!(scene.enemy.state(), EnemyState::Chase);
+
+        scene.enemy.hurt(&def, true, 0.9);
+        assert_eq!(scene.enemy.state(), EnemyState::Death);
+        for _ in 0..20 {
+            assert!(!scene.tick());
+        }
+        scene.enemy.hurt(&def, false, 0.1);
+        assert_eq!(scene.enemy.state(), EnemyState::Death);
+        assert_eq!(scene.actor().sprite.as_deref(), Some("DEATH2"));
+    }
+
+    #[test]
+    fn idle_without_target() {
+        let mut scene = Scene::new(0.5, 1.8);
+        scene.target.alive = false;
+        for _ in 0..10 {
+            scene.tick();
+        }
+        assert_eq!(scene.enemy.state(), EnemyState::Idle);
+
+        // Out of sight range
+        let mut scene = Scene::new(0.5, 1.8);
+        scene.def.sight = 1.0;
+        scene.tick();
+        assert_eq!(scene.enemy.state(), EnemyState::Idle);
+
+        // Gives up the chase when the target dies
+        let mut scene = Scene::new(0.5, 1.8);
+        scene.enemy.set_state(EnemyState::Chase);
+        scene.target.alive = false;
+        assert_eq!(scene.run(), (1, vec![]));
+        assert_eq!(scene.enemy.state(), EnemyState::Idle);
+    }
+}
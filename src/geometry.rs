@@ -0,0 +1,306 @@
+// 2D geometry on the xz plane (map coordinates)
+
+pub type Point = (f64, f64);
+
+pub const EPSILON: f64 = 1e-9;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Orientation {
+    CounterClockwise,
+    Clockwise,
+    Collinear,
+}
+
+// z component of (a - o) x (b - o)
+pub fn cross(o: Point, a: Point, b: Point) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+pub fn orientation(a: Point, b: Point, c: Point) -> Orientation {
+    let value = cross(a, b, c);
+    // scale tolerance with the size of the input so large maps behave like small ones
+    let scale = (b.0 - a.0).abs() + (b.1 - a.1).abs() + (c.0 - a.0).abs() + (c.1 - a.1).abs();
+    if value.abs() <= EPSILON * scale.max(1.0) {
+        Orientation::Collinear
+    } else if value > 0.0 {
+        Orientation::CounterClockwise
+    } else {
+        Orientation::Clockwise
+    }
+}
+
+pub fn distance(a: Point, b: Point) -> f64 {
+    f64::sqrt((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2))
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Segment {
+    pub a: Point,
+    pub b: Point,
+}
+
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug)]
+pub struct Intersection {
+    pub point: Point,
+    pub t: f64, // parameter along self
+    pub u: f64, // parameter along other
+}
+
+impl Segment {
+    pub fn new(a: Point, b: Point) -> Self {
+        Segment { a, b }
+    }
+
+    pub fn length(&self) -> f64 {
+        distance(self.a, self.b)
+    }
+
+    pub fn point_at(&self, t: f64) -> Point {
+        (
+            self.a.0 + (self.b.0 - self.a.0) * t,
+            self.a.1 + (self.b.1 - self.a.1) * t,
+        )
+    }
+
+    // Parameter of the projection of p, clamped onto the segment
+    pub fn project(&self, p: Point) -> f64 {
+        let d = (self.b.0 - self.a.0, self.b.1 - self.a.1);
+        let len_sq = d.0 * d.0 + d.1 * d.1;
+        if len_sq <= EPSILON * EPSILON {
+            return 0.0;
+        }
+
+        (((p.0 - self.a.0) * d.0 + (p.1 - self.a.1) * d.1) / len_sq).clamp(0.0, 1.0)
+    }
+
+    pub fn closest_point(&self, p: Point) -> Point {
+        self.point_at(self.project(p))
+    }
+
+    pub fn distance_to(&self, p: Point) -> f64 {
+        distance(p, self.closest_point(p))
+    }
+
+    // Endpoints count as touching. Collinear overlaps report the overlapping point nearest to self.a.
+    pub fn intersect(&self, other: &Segment) -> Option<Intersection> {
+        let r = (self.b.0 - self.a.0, self.b.1 - self.a.1);
+        let s = (other.b.0 - other.a.0, other.b.1 - other.a.1);
+        let qp = (other.a.0 - self.a.0, other.a.1 - self.a.1);
+
+        let denom = r.0 * s.1 - r.1 * s.0;
+        let qp_x_r = qp.0 * r.1 - qp.1 * r.0;
+
+        let scale = (r.0.abs() + r.1.abs()) * (s.0.abs() + s.1.abs());
+        if denom.abs() <= EPSILON * scale.max(EPSILON) {
+            if orientation(self.a, self.b, other.a) != Orientation::Collinear
+                || orientation(other.a, other.b, self.a) != Orientation::Collinear
+            {
+                return None; // parallel
+            }
+
+            return self.collinear_overlap(other);
+        }
+
+        let t = (qp.0 * s.1 - qp.1 * s.0) / denom;
+        let u = qp_x_r / denom;
+
+        let tolerance = EPSILON * 10.0;
+        if t < -tolerance || t > 1.0 + tolerance || u < -tolerance || u > 1.0 + tolerance {
+            return None;
+        }
+
+        let t = t.clamp(0.0, 1.0);
+        Some(Intersection {
+            point: self.point_at(t),
+            t,
+            u: u.clamp(0.0, 1.0),
+        })
+    }
+
+    fn collinear_overlap(&self, other: &Segment) -> Option<Intersection> {
+        // Degenerate segments are points
+        if self.length() <= EPSILON {
+            return if other.distance_to(self.a) <= EPSILON {
+                Some(Intersection {
+                    point: self.a,
+                    t: 0.0,
+                    u: other.project(self.a),
+                })
+            } else {
+                None
+            };
+        }
+
+        let r = (self.b.0 - self.a.0, self.b.1 - self.a.1);
+        let len_sq = r.0 * r.0 + r.1 * r.1;
+        let param = |p: Point| ((p.0 - self.a.0) * r.0 + (p.1 - self.a.1) * r.1) / len_sq;
+
+        let (t0, t1) = {
+            let (t0, t1) = (param(other.a), param(other.b));
+            (t0.min(t1), t0.max(t1))
+        };
+        if t1 < -EPSILON || t0 > 1.0 + EPSILON {
+            return None;
+        }
+
+        let t = t0.max(0.0);
+        let point = self.point_at(t);
+        Some(Intersection {
+            point,
+            t,
+            u: other.project(point),
+        })
+    }
+}
+
+// Even-odd rule, points on an edge count as inside
+pub fn point_in_polygon<T: Copy + Into<f64>>(p: Point, polygon: &[(T, T)]) -> bool {
+    let mut inside = false;
+    for i in 0..polygon.len() {
+        let a = (polygon[i].0.into(), polygon[i].1.into());
+        let j = (i + 1) % polygon.len();
+        let b = (polygon[j].0.into(), polygon[j].1.into());
+
+        if Segment::new(a, b).distance_to(p) <= EPSILON {
+            return true;
+        }
+
+        if (a.1 > p.1) != (b.1 > p.1) && p.0 < (b.0 - a.0) * (p.1 - a.1) / (b.1 - a.1) + a.0 {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// Strictly inside, points on an edge are outside. Triangle has to be ccw.
+pub fn point_in_triangle(p: Point, a: Point, b: Point, c: Point) -> bool {
+    orientation(a, b, p) == Orientation::CounterClockwise
+        && orientation(b, c, p) == Orientation::CounterClockwise
+        && orientation(c, a, p) == Orientation::CounterClockwise
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Point, b: Point) -> bool {
+        distance(a, b) < 1e-9
+    }
+
+    #[test]
+    fn orientation_of_points() {
+        let (a, b) = ((0.0, 0.0), (1.0, 0.0));
+        assert_eq!(orientation(a, b, (1.0, 1.0)), Orientation::CounterClockwise);
+        assert_eq!(orientation(a, b, (1.0, -1.0)), Orientation::Clockwise);
+        assert_eq!(orientation(a, b, (5.0, 0.0)), Orientation::Collinear);
+        // Rounding far away from the origin still counts as collinear
+        let far = 1e6 + 0.1;
+        assert_eq!(
+            orientation((far, far), (far + 0.3, far + 0.3), (far + 0.7, far + 0.7)),
+            Orientation::Collinear
+        );
+    }
+
+    #[test]
+    fn crossing_segments() {
+        let a = Segment::new((0.0, 0.0), (2.0, 2.0));
+        let b = Segment::new((0.0, 2.0), (2.0, 0.0));
+        let hit = a.intersect(&b).unwrap();
+        assert!(close(hit.point, (1.0, 1.0)));
+        assert!((hit.t - 0.5).abs() < 1e-9 && (hit.u - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn vertical_segments() {
+        let vertical = Segment::new((1.0, -1.0), (1.0, 1.0));
+        let horizontal = Segment::new((0.0, 0.0), (2.0, 0.0));
+        let hit = vertical.intersect(&horizontal).unwrap();
+        assert!(close(hit.point, (1.0, 0.0)));
+
+        let other = Segment::new((3.0, -1.0), (3.0, 1.0));
+        assert!(vertical.intersect(&other).is_none());
+    }
+
+    #[test]
+    fn parallel_segments() {
+        let a = Segment::new((0.0, 0.0), (2.0, 0.0));
+        let b = Segment::new((0.0, 1.0), (2.0, 1.0));
+        assert!(a.intersect(&b).is_none());
+    }
+
+    #[test]
+    fn collinear_segments() {
+        let a = Segment::new((0.0, 0.0), (2.0, 0.0));
+        let overlapping = Segment::new((3.0, 0.0), (1.0, 0.0));
+        let hit = a.intersect(&overlapping).unwrap();
+        assert!(close(hit.point, (1.0, 0.0)));
+        assert!((hit.t - 0.5).abs() < 1e-9);
+
+        let disjoint = Segment::new((3.0, 0.0), (4.0, 0.0));
+        assert!(a.intersect(&disjoint).is_none());
+    }
+
+    #[test]
+    fn touching_endpoints() {
+        let a = Segment::new((0.0, 0.0), (1.0, 0.0));
+        let b = Segment::new((1.0, 0.0), (1.0, 1.0));
+        let hit = a.intersect(&b).unwrap();
+        assert!(close(hit.point, (1.0, 0.0)));
+        assert!((hit.t - 1.0).abs() < 1e-9 && hit.u.abs() < 1e-9);
+
+        // Collinear segments sharing only an endpoint
+        let c = Segment::new((1.0, 0.0), (2.0, 0.0));
+        assert!(close(a.intersect(&c).unwrap().point, (1.0, 0.0)));
+    }
+
+    #[test]
+    fn zero_length_segments() {
+        let point = Segment::new((1.0, 0.0), (1.0, 0.0));
+        let line = Segment::new((0.0, 0.0), (2.0, 0.0));
+        let hit = point.intersect(&line).unwrap();
+        assert!(close(hit.point, (1.0, 0.0)));
+        assert!((hit.u - 0.5).abs() < 1e-9);
+
+        let away = Segment::new((1.0, 1.0), (1.0, 1.0));
+        assert!(away.intersect(&line).is_none());
+        assert!(line.intersect(&away).is_none());
+    }
+
+    #[test]
+    fn point_on_edge() {
+        let square = [(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)];
+        assert!(point_in_polygon((1.0, 0.0), &square));
+        assert!(point_in_polygon((2.0, 2.0), &square));
+        assert!(point_in_polygon((1.0, 1.0), &square));
+        assert!(!point_in_polygon((3.0, 1.0), &square));
+
+        let (a, b, c) = ((0.0, 0.0), (2.0, 0.0), (0.0, 2.0));
+        assert!(point_in_triangle((0.5, 0.5), a, b, c));
+        assert!(!point_in_triangle((1.0, 0.0), a, b, c));
+        assert!(!point_in_triangle((1.0, 1.0), a, b, c));
+        assert!(!point_in_triangle((2.0, 2.0), a, b, c));
+    }
+
+    #[test]
+    fn concave_polygon() {
+        // A U shape opening upwards
+        let shape = [
+            (0.0, 0.0),
+            (3.0, 0.0),
+            (3.0, 3.0),
+            (2.0, 3.0),
+            (2.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 3.0),
+            (0.0, 3.0),
+        ];
+        assert!(point_in_polygon((0.5, 2.0), &shape));
+        assert!(point_in_polygon((2.5, 2.0), &shape));
+        assert!(point_in_polygon((1.5, 0.5), &shape));
+        assert!(!point_in_polygon((1.5, 2.0), &shape));
+        // On the inner edges of the notch
+        assert!(point_in_polygon((1.5, 1.0), &shape));
+        assert!(point_in_polygon((1.0, 2.0), &shape));
+    }
+}
@@ -1,8 +1,10 @@
-use crate::{utils::deg_to_rad, INIT_HEIGHT, INIT_WIDTH};
+use crate::utils::deg_to_rad;
 use nalgebra_glm as ng;
 
 pub const UP: ng::DVec3 = ng::DVec3::new(0.0, 1.0, 0.0);
 
+const MAX_PITCH: f64 = 89.9;
+
 pub struct Camera {
     pos: ng::DVec3,
 
@@ -10,9 +12,6 @@ pub struct Camera {
     xz_front: ng::DVec3,
     yaw: f64,
     pitch: f64,
-
-    cur_last_pos: (f64, f64),
-    first_move: bool,
 }
 
 impl Camera {
@@ -24,9 +23,6 @@ impl Camera {
             xz_front: ng::DVec3::new(0.0, 0.0, -1.0),
             yaw: -90.0,
             pitch: 0.0,
-
-            cur_last_pos: (INIT_WIDTH as f64 / 2.0, INIT_HEIGHT as f64 / 2.0),
-            first_move: true,
         }
     }
 
@@ -36,6 +32,7 @@ impl Camera {
         self.pos.z = z;
         self.yaw = yaw;
         self.pitch = pitch;
+        self.rotate(0.0, 0.0);
     }
 
     pub fn put_xz(&mut self, x: f64, z: f64) {
@@ -47,33 +44,12 @@ impl Camera {
         self.pos.y = y;
     }
 
-    pub fn cur_mov(&mut self, x: f64, y: f64, sensitivity: f64) {
-        if self.first_move {
-            self.cur_last_pos = (x, y);
-            self.first_move = false;
-        }
-
-        let offset = (
-            (x - self.cur_last_pos.0) * sensitivity,
-            (self.cur_last_pos.1 - y) * sensitivity,
-        );
-        self.yaw += offset.0;
-        self.pitch += offset.1;
-        self.cur_last_pos = (x, y);
-
-        if self.pitch > 89.9 {
-            self.pitch = 89.9;
-        }
-        if self.pitch < -89.9 {
-            self.pitch = -89.9;
-        }
+    // yaw and pitch offsets in degrees
+    pub fn rotate(&mut self, yaw: f64, pitch: f64) {
+        self.yaw += yaw;
+        self.pitch = (self.pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH);
 
-        let direction = ng::DVec3::new(
-            f64::cos(deg_to_rad(self.yaw)) * f64::cos(deg_to_rad(self.pitch)),
-            f64::sin(deg_to_rad(self.pitch)),
-            f64::sin(deg_to_rad(self.yaw)) * f64::cos(deg_to_rad(self.pitch)),
-        );
-        self.front = ng::normalize(&direction);
+        self.front = front(self.yaw, self.pitch);
 
         let direction = ng::DVec3::new(
             f64::cos(deg_to_rad(self.yaw)),
@@ -87,15 +63,40 @@ impl Camera {
         &mut self.pos
     }
 
+    pub fn position(&self) -> ng::DVec3 {
+        self.pos
+    }
+
     pub fn view(&self) -> ng::DMat4 {
         ng::look_at(&self.pos, &(self.pos + self.front), &UP)
     }
 
-    pub fn set_first_move(&mut self, value: bool) {
-        self.first_move = value;
+    // View from between the position `from` (alpha = 0) and the current one (alpha = 1),
+    // turned by the look offset (yaw, pitch) which the simulation has not seen yet
+    pub fn view_interpolated(&self, from: &ng::DVec3, alpha: f64, look: (f64, f64)) -> ng::DMat4 {
+        let pos = ng::lerp(from, &self.pos, alpha);
+        let front = front(
+            self.yaw + look.0,
+            (self.pitch + look.1).clamp(-MAX_PITCH, MAX_PITCH),
+        );
+        ng::look_at(&pos, &(pos + front), &UP)
     }
 
     pub fn xz_front(&self) -> ng::DVec3 {
-        self.xz_front.clone()
+        self.xz_front
     }
+
+    // yaw, pitch in degrees
+    pub fn angles(&self) -> (f64, f64) {
+        (self.yaw, self.pitch)
+    }
+}
+
+pub fn front(yaw: f64, pitch: f64) -> ng::DVec3 {
+    let direction = ng::DVec3::new(
+        f64::cos(deg_to_rad(yaw)) * f64::cos(deg_to_rad(pitch)),
+        f64::sin(deg_to_rad(pitch)),
+        f64::sin(deg_to_rad(yaw)) * f64::cos(deg_to_rad(pitch)),
+    );
+    ng::normalize(&direction)
 }
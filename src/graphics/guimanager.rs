@@ -7,7 +7,7 @@ use super::{
     },
     shader::Shader,
 };
-use crate::{utils::Rect, INIT_HEIGHT, INIT_WIDTH};
+use crate::{player::MAX_HEALTH, utils::Rect, INIT_HEIGHT, INIT_WIDTH};
 
 pub const ATLAS_PATH: &str = "assets/textures/gui.png";
 
@@ -17,6 +17,9 @@ pub enum ActiveInterface {
     TEST,
     PAUSE,
     HELP,
+    MENU,
+    INTERMISSION,
+    GAMEOVER,
 }
 
 pub struct GuiManager {
@@ -28,6 +31,9 @@ pub struct GuiManager {
     test: Interface,
     pause: Interface,
     help: Interface,
+    menu: Interface,
+    intermission: Interface,
+    game_over: Interface,
 }
 
 impl GuiManager {
@@ -44,17 +50,21 @@ impl GuiManager {
             test: Interface::new(),
             pause: Interface::new(),
             help: Interface::new(),
+            menu: Interface::new(),
+            intermission: Interface::new(),
+            game_over: Interface::new(),
         };
 
-        test_gui(
-            &mut manager.test,
-            &Rect {
-                left: 0.0,
-                top: INIT_HEIGHT as f32,
-                right: INIT_WIDTH as f32,
-                bottom: 0.0,
-            },
-        );
+        let screen = Rect {
+            left: 0.0,
+            top: INIT_HEIGHT as f32,
+            right: INIT_WIDTH as f32,
+            bottom: 0.0,
+        };
+        test_gui(&mut manager.test, &screen);
+        menu_gui(&mut manager.menu, &screen);
+        intermission_gui(&mut manager.intermission, &screen);
+        game_over_gui(&mut manager.game_over, &screen);
 
         manager.shader.use_program();
         unsafe {
@@ -82,6 +92,9 @@ impl GuiManager {
             ActiveInterface::TEST => &self.test,
             ActiveInterface::PAUSE => &self.pause,
             ActiveInterface::HELP => &self.help,
+            ActiveInterface::MENU => &self.menu,
+            ActiveInterface::INTERMISSION => &self.intermission,
+            ActiveInterface::GAMEOVER => &self.game_over,
         }
     }
 
@@ -91,6 +104,9 @@ impl GuiManager {
             ActiveInterface::TEST => &mut self.test,
             ActiveInterface::PAUSE => &mut self.pause,
             ActiveInterface::HELP => &mut self.help,
+            ActiveInterface::MENU => &mut self.menu,
+            ActiveInterface::INTERMISSION => &mut self.intermission,
+            ActiveInterface::GAMEOVER => &mut self.game_over,
         }
     }
 
@@ -105,9 +121,39 @@ impl GuiManager {
     }
 
     pub fn set_hp(&self, num: u32) {
+        // crosshair and healthbar come first, overheal shows as full
+        self.set_points(2, num.min(MAX_HEALTH as u32));
+    }
+
+    pub fn set_armor(&self, num: u32) {
+        // after the health points and the armorbar
+        self.set_points(2 + 5 + 1, num);
+    }
+
+    // Level time on the intermission screen
+    pub fn set_time(&self, seconds: f64) {
+        let time = format!("{:>1$.2}", seconds.min(9999.99), TIME_DIGITS);
+        let mut data = self.intermission.vertices_clone();
+        data.truncate(TIME_DIGITS * 4);
+
+        // same corner order as the component models
+        for (i, glyph) in time.chars().enumerate() {
+            let left = glyph_left(glyph);
+            let right = left + CHAR_WIDTH / GUI_IMG_WIDTH;
+            data[i * 4].r = right;
+            data[i * 4 + 1].r = left;
+            data[i * 4 + 2].r = left;
+            data[i * 4 + 3].r = right;
+        }
+
+        self.intermission.update_vertex(0, &data);
+    }
+
+    // Shows the first num of the 5 point components starting at component first
+    fn set_points(&self, first: usize, num: u32) {
         let mut data = self.test.vertices_clone();
-        // remove every other vertex which does not belong to the hp points
-        for _ in 0..(2 * 4) {
+        // remove every other vertex which does not belong to the points
+        for _ in 0..(first * 4) {
             data.remove(0);
         }
         while data.len() > 5 * 4 {
@@ -121,8 +167,10 @@ impl GuiManager {
             }
         }
 
-        self.test
-            .update_vertex(2 * 4 * std::mem::size_of::<Vertex>() as isize, &data);
+        self.test.update_vertex(
+            first as isize * 4 * std::mem::size_of::<Vertex>() as isize,
+            &data,
+        );
     }
 }
 
@@ -5,6 +5,24 @@ use super::{guimanager::Component, renderable::gui::Gui as Interface};
 pub const GUI_IMG_WIDTH: f32 = 100.0;
 pub const GUI_IMG_HEIGHT: f32 = 100.0;
 
+// Text is a 3 * 5 pixel font, lines are pre-rendered in the atlas one below the other
+pub const CHAR_WIDTH: f32 = 4.0; // with the gap
+const CHAR_HEIGHT: f32 = 5.0;
+const TEXT_SCALE: f32 = 4.0;
+
+// Where the lines start in the atlas and how many characters they have
+const START_LINE: (f32, usize) = (40.0, 20); // PRESS ENTER TO START
+const QUIT_LINE: (f32, usize) = (46.0, 14); // ESCAPE TO QUIT
+const FINISHED_LINE: (f32, usize) = (52.0, 14); // LEVEL FINISHED
+const CONTINUE_LINE: (f32, usize) = (58.0, 17); // ENTER TO CONTINUE
+pub const DIGITS_TOP: f32 = 64.0; // 0123456789. and a space
+const SECONDS_LINE: (f32, usize) = (70.0, 7); // SECONDS
+const DIED_LINE: (f32, usize) = (76.0, 8); // YOU DIED
+const RESPAWN_LINE: (f32, usize) = (82.0, 16); // ENTER TO RESPAWN
+const MENU_LINE: (f32, usize) = (88.0, 15); // ESCAPE FOR MENU
+
+pub const TIME_DIGITS: usize = 7;
+
 pub fn test_gui(interface: &mut Interface, screen: &Rect<f32>) {
     // Crosshair 7 * 7
     interface.add(Component::new(
@@ -53,5 +71,114 @@ pub fn test_gui(interface: &mut Interface, screen: &Rect<f32>) {
         ));
     }
 
+    // Armorbar below the healthbar, same frame
+    interface.add(Component::new(
+        1.0 / screen.right * 6.0,
+        1.0 - 16.0 / screen.top * 6.0 - 14.0 / screen.top * 6.0,
+        46.0 / screen.right * 6.0,
+        14.0 / screen.top * 6.0,
+        Rect {
+            left: 0.0,
+            top: 10.0 / GUI_IMG_HEIGHT,
+            right: 46.0 / GUI_IMG_WIDTH,
+            bottom: 14.0 / GUI_IMG_HEIGHT,
+        },
+        0.1,
+    ));
+
+    // Armor (n * 8) * 8 // n * 8, 32
+    for n in 0..5 {
+        interface.add(Component::new(
+            n as f32 * 8.0 / screen.right * 6.0 + 4.0 / screen.right * 6.0,
+            1.0 - 19.0 / screen.top * 6.0 - 8.0 / screen.top * 6.0,
+            8.0 / screen.right * 6.0,
+            8.0 / screen.top * 6.0,
+            Rect {
+                left: n as f32 * 8.0 / GUI_IMG_WIDTH,
+                top: 32.0 / GUI_IMG_HEIGHT,
+                right: 8.0 / GUI_IMG_WIDTH,
+                bottom: 8.0 / GUI_IMG_HEIGHT,
+            },
+            0.2,
+        ));
+    }
+
+    interface.init();
+}
+
+pub fn menu_gui(interface: &mut Interface, screen: &Rect<f32>) {
+    text(interface, screen, 0.5, 0.55, START_LINE);
+    text(interface, screen, 0.5, 0.45, QUIT_LINE);
+
+    interface.init();
+}
+
+pub fn intermission_gui(interface: &mut Interface, screen: &Rect<f32>) {
+    // The time comes first so it can be set, right aligned in front of the seconds
+    let width = (TIME_DIGITS + 1 + SECONDS_LINE.1) as f32 * CHAR_WIDTH * TEXT_SCALE / screen.right;
+    let left = 0.5 - width / 2.0;
+    for n in 0..TIME_DIGITS {
+        interface.add(Component::new(
+            left + n as f32 * CHAR_WIDTH * TEXT_SCALE / screen.right,
+            0.5 - CHAR_HEIGHT * TEXT_SCALE / screen.top / 2.0,
+            CHAR_WIDTH * TEXT_SCALE / screen.right,
+            CHAR_HEIGHT * TEXT_SCALE / screen.top,
+            Rect {
+                left: glyph_left(' '),
+                top: DIGITS_TOP / GUI_IMG_HEIGHT,
+                right: CHAR_WIDTH / GUI_IMG_WIDTH,
+                bottom: CHAR_HEIGHT / GUI_IMG_HEIGHT,
+            },
+            0.0,
+        ));
+    }
+    text(
+        interface,
+        screen,
+        1.0 - left - SECONDS_LINE.1 as f32 * CHAR_WIDTH * TEXT_SCALE / screen.right / 2.0,
+        0.5,
+        SECONDS_LINE,
+    );
+
+    text(interface, screen, 0.5, 0.6, FINISHED_LINE);
+    text(interface, screen, 0.5, 0.4, CONTINUE_LINE);
+
     interface.init();
 }
+
+pub fn game_over_gui(interface: &mut Interface, screen: &Rect<f32>) {
+    text(interface, screen, 0.5, 0.6, DIED_LINE);
+    text(interface, screen, 0.5, 0.45, RESPAWN_LINE);
+    text(interface, screen, 0.5, 0.38, MENU_LINE);
+
+    interface.init();
+}
+
+// Where a digit, the point or a space starts in the atlas
+pub fn glyph_left(glyph: char) -> f32 {
+    let index = match glyph {
+        '.' => 10,
+        ' ' => 11,
+        digit => digit.to_digit(10).expect("Not a digit"),
+    };
+    index as f32 * CHAR_WIDTH / GUI_IMG_WIDTH
+}
+
+// A pre-rendered line centered at x and y
+fn text(interface: &mut Interface, screen: &Rect<f32>, x: f32, y: f32, (top, len): (f32, usize)) {
+    let width = len as f32 * CHAR_WIDTH * TEXT_SCALE / screen.right;
+    let height = CHAR_HEIGHT * TEXT_SCALE / screen.top;
+    interface.add(Component::new(
+        x - width / 2.0,
+        y - height / 2.0,
+        width,
+        height,
+        Rect {
+            left: 0.0,
+            top: top / GUI_IMG_HEIGHT,
+            right: len as f32 * CHAR_WIDTH / GUI_IMG_WIDTH,
+            bottom: CHAR_HEIGHT / GUI_IMG_HEIGHT,
+        },
+        0.0,
+    ));
+}
@@ -5,21 +5,25 @@ pub mod renderable;
 mod shader;
 
 use crate::utils::Rect;
+use crate::weapons::WeaponView;
 use nalgebra_glm as ng;
 use renderable::mapdata::Map;
+use renderable::sprites::{Sprite, SpriteRenderer, SPRITE_DIR};
 use renderable::RenderableShader;
 
 use self::guimanager::GuiManager;
 
 pub struct Graphics {
     screen_size: Rect<u32>,
-    paused: bool,
+    wireframe: bool,
     projection: ng::Mat4,
+    flash: f32, // brightens the whole scene
 
     gui_manager: GuiManager,
 
     map_shader: shader::Shader,
-    map: Map,
+    sprite_shader: shader::Shader,
+    sprites: SpriteRenderer,
 }
 
 impl Graphics {
@@ -32,60 +36,115 @@ impl Graphics {
             gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
         }
 
-        let mut graphics = Graphics {
+        Graphics {
             screen_size: Rect::new(0, 0, crate::INIT_WIDTH, crate::INIT_HEIGHT),
-            paused: false,
+            wireframe: false,
             projection: ng::perspective(
                 crate::INIT_WIDTH as f32 / crate::INIT_HEIGHT as f32,
                 45.0 * ng::pi::<f32>() / 180.0,
                 0.001,
                 100.0,
             ),
+            flash: 0.0,
 
-            gui_manager: GuiManager::new(guimanager::ActiveInterface::TEST),
+            gui_manager: GuiManager::new(guimanager::ActiveInterface::NONE),
 
             map_shader: shader::Shader::new(
                 "assets/shaders/map.glsl.vert",
                 "assets/shaders/map.glsl.frag",
             ),
-            map: Map::new(),
-        };
-
-        graphics
-            .map
-            .load_from_file("test2.json", &graphics.map_shader);
+            sprite_shader: shader::Shader::new(
+                "assets/shaders/sprite.glsl.vert",
+                "assets/shaders/sprite.glsl.frag",
+            ),
+            sprites: SpriteRenderer::load(SPRITE_DIR),
+        }
+    }
 
-        graphics
+    // Sends the meshes of a loaded map to the GPU
+    pub fn upload_map(&self, map: &mut Map) {
+        map.create(&self.map_shader);
     }
 
-    pub fn update(&mut self, delta_time: f32, view: &ng::Mat4) {
-        if !self.paused {
-            self.map.update(delta_time);
-        }
+    // Sends meshes which changed while playing to the GPU
+    pub fn update_map(&self, map: &mut Map) {
+        map.upload_changes();
+    }
 
+    pub fn clear(&self) {
         unsafe {
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
+    }
 
-        if !self.paused {
-            self.map_shader.use_program();
-            self.map_shader.set_mat4("projection", &self.projection);
-            self.map_shader.set_mat4("view", view);
+    // dimmed darkens the map behind menus
+    pub fn render_map(&self, map: &Map, view: &ng::Mat4, dimmed: bool) {
+        self.map_shader.use_program();
+        self.map_shader.set_mat4("projection", &self.projection);
+        self.map_shader.set_mat4("view", view);
+        self.map_shader.set_i32("bPause", &(dimmed as i32));
+        self.map_shader.set_f32("flash", &self.flash);
+
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
         }
+        map.render(&self.map_shader);
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+        }
+    }
+
+    // Draws over the map with the same view, walls in front hide them
+    pub fn render_sprites(&self, sprites: &[Sprite], view: &ng::Mat4, dimmed: bool) {
+        self.sprite_shader.use_program();
+        self.sprite_shader.set_mat4("projection", &self.projection);
+        self.sprite_shader.set_mat4("view", view);
+        self.sprite_shader.set_i32("bPause", &(dimmed as i32));
+        self.sprite_shader.set_f32("flash", &self.flash);
+
+        let camera = ng::inverse(view).column(3).xyz();
         unsafe {
             gl::Enable(gl::DEPTH_TEST);
         }
-        self.map.render(&self.map_shader);
+        self.sprites.render(&self.sprite_shader, sprites, &camera);
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+        }
+    }
+
+    // The weapon in the hands of the player, over the world and under the HUD
+    pub fn render_weapon(&self, weapon: &WeaponView, light: f32, dimmed: bool) {
+        self.sprite_shader.use_program();
+        self.sprite_shader.set_i32("bPause", &(dimmed as i32));
+        self.sprite_shader.set_f32("flash", &self.flash);
+
+        let aspect = self.screen_size.right as f32 / self.screen_size.bottom as f32;
+        // Screen heights are 2 from -1 to 1
+        let position = ng::vec2(
+            weapon.offset.0 as f32 * 2.0 / aspect,
+            -1.0 + weapon.offset.1 as f32 * 2.0,
+        );
+        self.sprites
+            .render_overlay(&self.sprite_shader, &weapon.frame, position, aspect, light);
+        if let Some(flash) = &weapon.flash {
+            self.sprites
+                .render_overlay(&self.sprite_shader, flash, position, aspect, light);
+        }
+    }
 
+    // Lights up the scene for muzzle flashes, 0 is off
+    pub fn set_flash(&mut self, flash: f32) {
+        self.flash = flash;
+    }
+
+    pub fn render_gui(&self) {
         unsafe {
+            gl::Enable(gl::DEPTH_TEST);
             gl::Enable(gl::BLEND);
         }
         self.gui_manager.render();
         unsafe {
             gl::Disable(gl::BLEND);
-        }
-
-        unsafe {
             gl::Disable(gl::DEPTH_TEST);
         }
     }
@@ -108,26 +167,18 @@ impl Graphics {
         self.screen_size.bottom = height;
     }
 
-    pub fn pause(&mut self) {
-        self.map_shader.use_program();
-        self.map_shader.set_i32("bPause", &1);
-        self.paused = true;
-        self.gui_manager.set_gui(guimanager::ActiveInterface::PAUSE);
-    }
-
-    pub fn unpause(&mut self) {
-        self.map_shader.use_program();
-        self.map_shader.set_i32("bPause", &0);
-        self.paused = false;
-        self.gui_manager.set_gui(guimanager::ActiveInterface::TEST);
-    }
-
-    pub fn spawn(&self) -> (f32, f32, f32) {
-        self.map.spawn
+    pub fn toggle_wireframe(&mut self) {
+        self.wireframe = !self.wireframe;
+        unsafe {
+            gl::PolygonMode(
+                gl::FRONT_AND_BACK,
+                if self.wireframe { gl::LINE } else { gl::FILL },
+            );
+        }
     }
 
-    pub fn map(&self) -> &Map {
-        &self.map
+    pub fn set_interface(&mut self, interface: guimanager::ActiveInterface) {
+        self.gui_manager.set_gui(interface);
     }
 
     pub fn gui_manager(&self) -> &guimanager::GuiManager {
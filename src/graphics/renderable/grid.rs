@@ -0,0 +1,82 @@
+use super::mapdata::Sector;
+
+pub const CELL_SIZE: f32 = 1.0;
+
+// Uniform grid over the map bounds, every cell lists the sectors whose bounding box overlaps it
+#[derive(Clone, Default)]
+pub struct SectorGrid {
+    origin: (f32, f32), // x, z
+    columns: usize,
+    rows: usize,
+    cells: Vec<Vec<u32>>,
+}
+
+impl SectorGrid {
+    pub fn build(sectors: &[Sector]) -> Self {
+        let mut grid = SectorGrid::default();
+
+        let mut min = (f32::MAX, f32::MAX);
+        let mut max = (f32::MIN, f32::MIN);
+        for sector in sectors {
+            for corner in &sector.corners {
+                min = (min.0.min(corner.0), min.1.min(corner.1));
+                max = (max.0.max(corner.0), max.1.max(corner.1));
+            }
+        }
+
+        if min.0 > max.0 || min.1 > max.1 {
+            return grid;
+        }
+
+        grid.origin = min;
+        grid.columns = ((max.0 - min.0) / CELL_SIZE) as usize + 1;
+        grid.rows = ((max.1 - min.1) / CELL_SIZE) as usize + 1;
+        grid.cells = vec![Vec::new(); grid.columns * grid.rows];
+
+        for (i, sector) in sectors.iter().enumerate() {
+            let mut s_min = (f32::MAX, f32::MAX);
+            let mut s_max = (f32::MIN, f32::MIN);
+            for corner in &sector.corners {
+                s_min = (s_min.0.min(corner.0), s_min.1.min(corner.1));
+                s_max = (s_max.0.max(corner.0), s_max.1.max(corner.1));
+            }
+
+            // Sectors without corners can not contain anything
+            let (Some((c0, r0)), Some((c1, r1))) =
+                (grid.cell(s_min.0, s_min.1), grid.cell(s_max.0, s_max.1))
+            else {
+                continue;
+            };
+            for row in r0..=r1 {
+                for column in c0..=c1 {
+                    grid.cells[row * grid.columns + column].push(i as u32);
+                }
+            }
+        }
+
+        grid
+    }
+
+    fn cell(&self, x: f32, z: f32) -> Option<(usize, usize)> {
+        let column = (x - self.origin.0) / CELL_SIZE;
+        let row = (z - self.origin.1) / CELL_SIZE;
+        if column < 0.0 || row < 0.0 {
+            return None;
+        }
+
+        let (column, row) = (column as usize, row as usize);
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+
+        Some((column, row))
+    }
+
+    // Sectors which may contain the point
+    pub fn candidates(&self, x: f32, z: f32) -> &[u32] {
+        match self.cell(x, z) {
+            Some((column, row)) => &self.cells[row * self.columns + column],
+            None => &[],
+        }
+    }
+}
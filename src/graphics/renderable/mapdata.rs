@@ -1,8 +1,16 @@
+use super::grid::SectorGrid;
 use super::sector::{Sector as SectorMesh, TextureData, Vertex, CEILING, FLOOR, GATE, WALL};
 use super::RenderableShader;
+use crate::damage::{Damage, DamageType};
+use crate::doors::DoorDef;
 use crate::graphics::shader::Shader;
+use crate::geometry::{orientation, point_in_polygon, point_in_triangle, Orientation};
+use crate::lifts::LiftDef;
+use crate::things::{Thing, ThingKind};
+use crate::triggers::TriggerDef;
 use crate::utils::{get_item, index_of};
-use nalgebra_glm as ng;
+
+const MIN_TEXTURE_HEIGHT: f32 = 0.01; // keeps the walls of closed doors from dividing by zero
 
 pub struct Map {
     pub path: String,
@@ -10,6 +18,10 @@ pub struct Map {
     pub description: String,
     pub sectors: Vec<Sector>,
     pub spawn: (f32, f32, f32),
+    pub checkpoints: Vec<(f32, f32, f32)>, // x, y, z like the spawn
+    pub spawn_angle: f32,                  // degrees, from a player start
+    pub things: Vec<Thing>,
+    grid: SectorGrid,
 }
 
 #[derive(Clone)]
@@ -18,6 +30,12 @@ pub struct Sector {
     pub ceiling: f32,
     pub corners: Vec<Corner>,
     pub gates: Vec<Gate>,
+    pub damage: Option<Damage>, // hurts whoever stands on the floor
+    pub light: f32,             // brightness of everything inside, 1 is full
+    pub door: Option<DoorDef>,
+    pub lift: Option<LiftDef>,
+    pub tag: Option<u32>, // triggers act on all sectors with their tag
+    pub triggers: Vec<TriggerDef>,
     pub mesh: SectorMesh,
 }
 
@@ -28,6 +46,7 @@ pub struct Gate {
     pub own: u32,
     pub target_sector: u32,
     pub target_gate: u32,
+    pub blocks_sound: bool,
 }
 
 impl Map {
@@ -38,546 +57,413 @@ impl Map {
             description: String::new(),
             sectors: Vec::new(),
             spawn: (0.0, 0.0, 0.0),
+            checkpoints: Vec::new(),
+            spawn_angle: 0.0,
+            things: Vec::new(),
+            grid: SectorGrid::default(),
         }
     }
 
-    pub fn load_from_file(&mut self, path: &str, shaders: &Shader) {
+    // Parses the map and builds the meshes without touching OpenGL, upload them with `create`
+    pub fn load(&mut self, path: &str) {
         self.path = format!("assets/maps/{path}");
 
-        let mut map_json;
-        {
-            let input =
-                std::fs::read_to_string(&self.path).expect("Could not find file '{self.path}'");
-            map_json = json::parse(&input).unwrap();
-        }
+        let input = std::fs::read_to_string(&self.path).expect("Could not find file '{self.path}'");
+        self.parse(json::parse(&input).unwrap());
+    }
+
+    // The contents of a map file
+    pub(crate) fn parse(&mut self, mut map_json: json::JsonValue) {
         self.name = map_json["name"].take_string().unwrap();
         self.description = map_json["description"].take_string().unwrap();
         self.spawn.2 = map_json["spawn"].pop().as_f32().unwrap();
         self.spawn.1 = map_json["spawn"].pop().as_f32().unwrap();
         self.spawn.0 = map_json["spawn"].pop().as_f32().unwrap();
+        for checkpoint in map_json["checkpoints"].members() {
+            self.checkpoints.push((
+                checkpoint[0].as_f32().unwrap(),
+                checkpoint[1].as_f32().unwrap(),
+                checkpoint[2].as_f32().unwrap(),
+            ));
+        }
 
         for sector_json in map_json["sectors"].members_mut() {
             let floor = sector_json["floor"].as_f32().unwrap();
             let ceiling = sector_json["ceiling"].as_f32().unwrap();
+            let light = sector_json["light"].as_f32().unwrap_or(1.0);
+            let damage = if sector_json["damage"].is_null() {
+                None
+            } else {
+                let kind = match sector_json["damage"]["type"].as_str() {
+                    Some(name) => DamageType::from_name(name).expect("Unknown damage type"),
+                    None => DamageType::Environmental,
+                };
+                Some(Damage::new(
+                    sector_json["damage"]["amount"].as_i32().unwrap(),
+                    kind,
+                ))
+            };
+            // Doors start closed, the ceiling is where they open to
+            let door = if sector_json["door"].is_null() {
+                None
+            } else {
+                Some(DoorDef::parse(&sector_json["door"], ceiling).expect("Invalid door"))
+            };
+            // Lifts start at the floor and move to their other height
+            let lift = if sector_json["lift"].is_null() {
+                None
+            } else {
+                Some(LiftDef::parse(&sector_json["lift"], floor).expect("Invalid lift"))
+            };
 
-            let mut corners: Vec<Corner> = Vec::new();
-            let mut gates: Vec<Gate> = Vec::new();
+            let triggers: Vec<TriggerDef> = sector_json["triggers"]
+                .members()
+                .map(|trigger| TriggerDef::parse(trigger).expect("Invalid trigger"))
+                .collect();
 
-            let mut wall_vertices: Vec<Vertex> = Vec::new();
-            let mut wall_indices: Vec<u32> = Vec::new();
-
-            let mut planes_vertices: Vec<Vertex> = Vec::new();
-            let mut planes_indices: Vec<u32> = Vec::new();
-
-            //
-            // Create mesh
-            //
-            // Create vertices on floor and ceiling level
-            let mut last = (0.0, 0.0);
-            let mut diff = 0.0;
-            let mut b = true;
-            let mut fx = 0.0;
-            let mut fz = 0.0;
+            let mut corners: Vec<Corner> = Vec::new();
             for corner_json in sector_json["corners"].members_mut() {
                 let z = corner_json.pop().as_f32().unwrap();
                 let x = corner_json.pop().as_f32().unwrap();
-                if b {
-                    fx = x;
-                    fz = z;
-                    b = false;
-                }
-
-                diff += f32::sqrt(f32::abs(x - last.0).powi(2) + f32::abs(z - last.1).powi(2))
-                    / (ceiling - floor);
+                corners.push((x, z));
+            }
 
-                wall_vertices.push(Vertex {
-                    x,
-                    y: floor,
-                    z: z * -1.0,
-                    s: diff,
-                    t: 0.0,
-                    vtype: WALL,
+            let mut gates: Vec<Gate> = Vec::new();
+            for gate in sector_json["gates"].members() {
+                gates.push(Gate {
+                    own: gate["own"].as_u32().unwrap(),
+                    target_sector: gate["targetSector"].as_u32().unwrap(),
+                    target_gate: gate["targetGate"].as_u32().unwrap(),
+                    blocks_sound: gate["blocksSound"].as_bool().unwrap_or(false),
                 });
+            }
+            if !valid_corners(&corners, &gates) {
+                panic!(
+                    "Sector {} needs at least 3 valid corners and an edge for every gate",
+                    self.sectors.len()
+                );
+            }
+
+            let texture = |name: &str| {
+                let path = match sector_json["textures"][name].as_str() {
+                    Some(s) => format!("assets/textures/{s}"),
+                    None => "assets/textures/fallback.png".to_string(),
+                };
+                (path, 0)
+            };
+            let textures = TextureData {
+                wall: texture("wall"),
+                floor: texture("floor"),
+                ceiling: texture("ceiling"),
+                gate: texture("gate"),
+            };
+
+            self.sectors.push(Sector {
+                floor,
+                ceiling: if door.is_some() { floor } else { ceiling },
+                corners,
+                gates,
+                damage,
+                light,
+                door,
+                lift,
+                tag: sector_json["tag"].as_u32(),
+                triggers,
+                mesh: SectorMesh::new(textures),
+            });
+        }
+
+        // The gate steps need all sectors
+        for index in 0..self.sectors.len() {
+            self.build_mesh(index, true);
+        }
+
+        self.grid = SectorGrid::build(&self.sectors);
+
+        self.load_things(&map_json["things"]);
+        // Teleports need the things
+        self.check_triggers();
+        // A player start replaces the spawn
+        if let Some(start) = self
+            .things
+            .iter()
+            .find(|thing| matches!(thing.kind, ThingKind::PlayerStart))
+        {
+            self.spawn = start.position;
+            self.spawn_angle = start.angle;
+        }
+    }
+
+    // Moves the floor and ceiling of a sector, the meshes of it and its neighbours get rebuilt
+    pub fn set_heights(&mut self, sector: u32, floor: f32, ceiling: f32) {
+        let index = sector as usize;
+        self.sectors[index].floor = floor;
+        self.sectors[index].ceiling = ceiling;
+        self.rebuild(sector, false);
+    }
+
+    // Reshapes a sector, the gates keep their edges so the number of corners should stay the same.
+    // Shared corners have to be moved in the neighbours as well.
+    // Returns false and changes nothing if the corners are not valid for the sector
+    pub fn set_corners(&mut self, sector: u32, corners: Vec<Corner>) -> bool {
+        let index = sector as usize;
+        if !valid_corners(&corners, &self.sectors[index].gates) {
+            return false;
+        }
+        self.sectors[index].corners = corners;
+        self.rebuild(sector, true);
+        self.grid = SectorGrid::build(&self.sectors);
+        true
+    }
+
+    // Kind is FLOOR, CEILING, WALL or GATE and the name like in the map files,
+    // returns false for unknown kinds or missing images
+    pub fn set_texture(&mut self, sector: u32, kind: u32, name: &str) -> bool {
+        let Some(path) = texture_path(name) else {
+            return false;
+        };
+        self.sectors[sector as usize].mesh.set_texture(kind, path)
+    }
+
+    // The sector and the steps of its neighbours, which only get triangulated again for new corners
+    fn rebuild(&mut self, sector: u32, triangulate: bool) {
+        self.build_mesh(sector as usize, triangulate);
+        let neighbours: Vec<u32> = self.sectors[sector as usize]
+            .gates
+            .iter()
+            .map(|gate| gate.target_sector)
+            .collect();
+        for neighbour in neighbours {
+            if neighbour != sector {
+                self.build_mesh(neighbour as usize, false);
+            }
+        }
+    }
+
+    // Sends rebuilt meshes to the GPU
+    pub fn upload_changes(&mut self) {
+        for sector in &mut self.sectors {
+            sector.mesh.upload();
+        }
+    }
+
+    // Walls, the steps down to lower neighbours and the planes, without touching OpenGL.
+    // The planes keep their triangles unless triangulate is set
+    fn build_mesh(&mut self, index: usize, triangulate: bool) {
+        let sector = &self.sectors[index];
+        let (floor, ceiling) = (sector.floor, sector.ceiling);
+        // Closed doors have no height
+        let height = (ceiling - floor).max(MIN_TEXTURE_HEIGHT);
+        let length = |a: Corner, b: Corner| f32::sqrt((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2));
+
+        let mut wall_vertices: Vec<Vertex> = Vec::new();
+        let mut wall_indices: Vec<u32> = Vec::new();
+        let mut planes_vertices: Vec<Vertex> = Vec::new();
+
+        // The first corner comes again at the end so the texture lines up
+        let mut last = (0.0, 0.0);
+        let mut diff = 0.0;
+        for &(x, z) in sector.corners.iter().chain(sector.corners.first()) {
+            diff += length(last, (x, z)) / height;
+            for (y, t) in [(floor, 0.0), (ceiling, 1.0)] {
                 wall_vertices.push(Vertex {
                     x,
-                    y: ceiling,
+                    y,
                     z: z * -1.0,
                     s: diff,
-                    t: 1.0,
+                    t,
                     vtype: WALL,
                 });
+            }
+            last = (x, z);
+        }
 
+        for &(x, z) in &sector.corners {
+            for (y, vtype) in [(floor, FLOOR), (ceiling, CEILING)] {
                 planes_vertices.push(Vertex {
                     x,
-                    y: floor,
+                    y,
                     z: z * -1.0,
                     s: x,
                     t: z,
-                    vtype: FLOOR,
+                    vtype,
                 });
-                planes_vertices.push(Vertex {
-                    x,
-                    y: ceiling,
-                    z: z * -1.0,
-                    s: x,
-                    t: z,
-                    vtype: CEILING,
-                });
-
-                corners.push((x, z).clone());
-
-                last = (x, z);
-            }
-
-            // Push first vetex again with different s, t to prevent the texture to have not lining up
-            diff += f32::sqrt(f32::abs(fx - last.0).powi(2) + f32::abs(fz - last.1).powi(2))
-                / (ceiling - floor);
-            wall_vertices.push(Vertex {
-                x: fx,
-                y: floor,
-                z: fz * -1.0,
-                s: diff,
-                t: 0.0,
-                vtype: WALL,
-            });
-            wall_vertices.push(Vertex {
-                x: fx,
-                y: ceiling,
-                z: fz * -1.0,
-                s: diff,
-                t: 1.0,
-                vtype: WALL,
-            });
-
-            // Get Gates for Indices
-            for _ in 0..sector_json["gates"].len() {
-                let gate = sector_json["gates"].pop();
-                gates.insert(
-                    0,
-                    Gate {
-                        own: gate["own"].as_u32().unwrap(),
-                        target_sector: gate["targetSector"].as_u32().unwrap(),
-                        target_gate: gate["targetGate"].as_u32().unwrap(),
-                    },
-                );
             }
+        }
 
-            // Create WALL Indices
-            let mut gates_index: Vec<u32> = Vec::new();
-            for ele in gates.clone() {
-                gates_index.push(ele.own);
+        for i in 0..sector.corners.len() as u32 {
+            if !sector.gates.iter().any(|gate| gate.own == i) {
+                wall_indices.extend([i * 2, i * 2 + 1, i * 2 + 2, i * 2 + 1, i * 2 + 3, i * 2 + 2]);
             }
+        }
 
-            for i in 0..corners.len() as u32 {
-                if !gates_index.contains(&i) {
-                    wall_indices.push(i * 2);
-                    wall_indices.push(i * 2 + 1);
-                    wall_indices.push(i * 2 + 2);
-                    wall_indices.push(i * 2 + 1);
-                    wall_indices.push(i * 2 + 3);
-                    wall_indices.push(i * 2 + 2);
+        // WALLS between sectors (Gates), the higher floor and the lower ceiling have the step
+        for gate in &sector.gates {
+            let target = &self.sectors[gate.target_sector as usize];
+            let corner0 = sector.corners[gate.own as usize];
+            let corner1 = sector.corners[(gate.own as usize + 1) % sector.corners.len()];
+
+            for (own, other, below) in [
+                (floor, target.floor, true),
+                (ceiling, target.ceiling, false),
+            ] {
+                if (below && own <= other) || (!below && own >= other) {
+                    continue;
                 }
-            }
 
-            // PLANES indices (polygon triangulation) -> Ear Clipping
-            /*
-            Rules:
-             - ccw
-             - defined in order
-             - 2 edges
-             - no intersection
-             - no holes
-             - not colinear (angle != 180°)
-             */
-            {
-                let mut index_list: Vec<(f32, f32)> = corners.clone();
-                while index_list.len() > 3 {
-                    for i in 0..index_list.len() as isize {
-                        // find valid ear
-                        {
-                            let a = &index_list[i as usize];
-                            let b = get_item(&index_list, i + 1).unwrap();
-                            let c = get_item(&index_list, i - 1).unwrap();
-
-                            let va = ng::vec2(a.0, a.1);
-                            let vb = ng::vec2(b.0, b.1);
-                            let vc = ng::vec2(c.0, c.1);
-
-                            // check if angle abc is convex
-                            {
-                                let va_to_vb = vb - va;
-                                let va_to_vc = vc - va;
-                                if ng::cross(
-                                    &ng::vec2_to_vec3(&va_to_vb),
-                                    &ng::vec2_to_vec3(&va_to_vc),
-                                )
-                                .z < 0.0
-                                {
-                                    continue;
-                                }
-                            }
-
-                            // check if other points are in triangle
-                            let mut in_triangle = false;
-                            for j in 0..index_list.len() as isize {
-                                if j == i || j == i - 1 || j == i + 1 {
-                                    continue;
-                                }
-
-                                let p =
-                                    ng::vec2(index_list[j as usize].0, index_list[j as usize].1);
-
-                                if ng::cross(
-                                    &ng::vec2_to_vec3(&(vc - va)),
-                                    &ng::vec2_to_vec3(&(p - va)),
-                                )
-                                .z > 0.0
-                                    && ng::cross(
-                                        &ng::vec2_to_vec3(&(vb - vc)),
-                                        &ng::vec2_to_vec3(&(p - vc)),
-                                    )
-                                    .z > 0.0
-                                    && ng::cross(
-                                        &ng::vec2_to_vec3(&(va - vb)),
-                                        &ng::vec2_to_vec3(&(p - vb)),
-                                    )
-                                    .z > 0.0
-                                {
-                                    in_triangle = true;
-                                    break;
-                                }
-                            }
-
-                            if in_triangle {
-                                continue;
-                            }
-
-                            // Add trinangles to Element Buffer
-                            let ta = index_of(&corners, a).unwrap() as u32 * 2;
-                            let tb = index_of(&corners, b).unwrap() as u32 * 2;
-                            let tc = index_of(&corners, c).unwrap() as u32 * 2;
-
-                            // floor
-                            planes_indices.push(ta);
-                            planes_indices.push(tb);
-                            planes_indices.push(tc);
-
-                            // ceiling
-                            planes_indices.push(ta + 1);
-                            planes_indices.push(tc + 1);
-                            planes_indices.push(tb + 1);
-                        } // let reference run out of scope
-
-                        // Remove i from Indexlist
-                        index_list.remove(i as usize);
-                        index_list.shrink_to_fit();
-
-                        break;
+                let s = length(corner0, corner1) / (own - other);
+                let offset = wall_vertices.len() as u32;
+                for ((x, z), s) in [(corner0, 0.0), (corner1, s)] {
+                    for (y, t) in [(own, 1.0), (other, 0.0)] {
+                        wall_vertices.push(Vertex {
+                            x,
+                            y,
+                            z: -1.0 * z,
+                            s,
+                            t,
+                            vtype: GATE,
+                        });
                     }
                 }
 
-                // Last triangle
-                let ta = index_of(&corners, &index_list[0]).unwrap() as u32 * 2;
-                let tb = index_of(&corners, &index_list[1]).unwrap() as u32 * 2;
-                let tc = index_of(&corners, &index_list[2]).unwrap() as u32 * 2;
-
-                // floor
-                planes_indices.push(ta);
-                planes_indices.push(tb);
-                planes_indices.push(tc);
-
-                // ceiling
-                planes_indices.push(ta + 1);
-                planes_indices.push(tc + 1);
-                planes_indices.push(tb + 1);
+                // 0 top right, 1 bottom right, 2 top left, 3 bottom left, facing the other sector
+                if below {
+                    wall_indices.extend([3, 2, 1, 2, 0, 1].map(|i| offset + i));
+                } else {
+                    wall_indices.extend([3, 1, 2, 2, 1, 0].map(|i| offset + i));
+                }
             }
+        }
 
-            //
-            // Initilize and send first bumb of data
-            //
-            let sector = Sector {
-                floor,
-                ceiling,
-                corners,
-                gates,
-                mesh: SectorMesh::new(
-                    wall_vertices,
-                    wall_indices,
-                    planes_vertices,
-                    planes_indices,
-                    TextureData {
-                        wall: (
-                            match sector_json["textures"]["wall"].as_str() {
-                                Some(s) => format!("assets/textures/{s}").to_string(),
-                                None => "assets/textures/fallback.png".to_string(),
-                            },
-                            0,
-                        ),
-                        floor: (
-                            match sector_json["textures"]["floor"].as_str() {
-                                Some(s) => format!("assets/textures/{s}").to_string(),
-                                None => "assets/textures/fallback.png".to_string(),
-                            },
-                            0,
-                        ),
-                        ceiling: (
-                            match sector_json["textures"]["ceiling"].as_str() {
-                                Some(s) => format!("assets/textures/{s}").to_string(),
-                                None => "assets/textures/fallback.png".to_string(),
-                            },
-                            0,
-                        ),
-                        gate: (
-                            match sector_json["textures"]["gate"].as_str() {
-                                Some(s) => format!("assets/textures/{s}").to_string(),
-                                None => "assets/textures/fallback.png".to_string(),
-                            },
-                            0,
-                        ),
-                    },
-                ),
-            };
+        let planes_indices = triangulate.then(|| triangulate_corners(&sector.corners));
+        self.sectors[index].mesh.set_geometry(
+            wall_vertices,
+            wall_indices,
+            planes_vertices,
+            planes_indices,
+        );
+    }
 
-            self.sectors.push(sector);
-        }
+    // x and z in map coordinates (like the corners)
+    pub fn sector_at(&self, x: f32, z: f32) -> Option<u32> {
+        self.grid
+            .candidates(x, z)
+            .iter()
+            .find(|i| point_in_polygon((x as f64, z as f64), &self.sectors[**i as usize].corners))
+            .copied()
+    }
+}
 
-        {
-            // WALLS between sectors (Gates)
-            let cpy_sectors = self.sectors.clone();
-            for sector in &mut self.sectors {
-                // Indices between sectors
-                for gate in &sector.gates {
-                    // Floor
-                    if sector.floor > cpy_sectors[gate.target_sector as usize].floor {
-                        sector.mesh.wall_vertices.push(Vertex {
-                            x: sector.corners[gate.own as usize].0,
-                            y: sector.floor,
-                            z: -1.0 * sector.corners[gate.own as usize].1,
-                            s: 0.0,
-                            t: 1.0,
-                            vtype: GATE,
-                        }); // 0 top right
-
-                        sector.mesh.wall_vertices.push(Vertex {
-                            x: sector.corners[gate.own as usize].0,
-                            y: cpy_sectors[gate.target_sector as usize].floor,
-                            z: -1.0 * sector.corners[gate.own as usize].1,
-                            s: 0.0,
-                            t: 0.0,
-                            vtype: GATE,
-                        }); // 1 bottom right
-
-                        // if is between last and first
-                        if gate.own as i32 == sector.corners.len() as i32 - 1 {
-                            sector.mesh.wall_vertices.push(Vertex {
-                                x: sector.corners[0].0,
-                                y: sector.floor,
-                                z: -1.0 * sector.corners[0].1,
-                                s: f32::sqrt(
-                                    f32::abs(
-                                        sector.corners[0].0 - sector.corners[gate.own as usize].0,
-                                    )
-                                    .powi(2)
-                                        + f32::abs(
-                                            sector.corners[0].1
-                                                - sector.corners[gate.own as usize].1,
-                                        )
-                                        .powi(2),
-                                ) / (sector.floor
-                                    - cpy_sectors[gate.target_sector as usize].floor),
-                                t: 1.0,
-                                vtype: GATE,
-                            }); // 2 top left
-
-                            sector.mesh.wall_vertices.push(Vertex {
-                                x: sector.corners[0].0,
-                                y: cpy_sectors[gate.target_sector as usize].floor,
-                                z: -1.0 * sector.corners[0].1,
-                                s: f32::sqrt(
-                                    f32::abs(
-                                        sector.corners[0].0 - sector.corners[gate.own as usize].0,
-                                    )
-                                    .powi(2)
-                                        + f32::abs(
-                                            sector.corners[0].1
-                                                - sector.corners[gate.own as usize].1,
-                                        )
-                                        .powi(2),
-                                ) / (sector.floor
-                                    - cpy_sectors[gate.target_sector as usize].floor),
-                                t: 0.0,
-                                vtype: GATE,
-                            }); // 3 bottom left
-                        } else {
-                            sector.mesh.wall_vertices.push(Vertex {
-                                x: sector.corners[gate.own as usize + 1].0,
-                                y: sector.floor,
-                                z: -1.0 * sector.corners[gate.own as usize + 1].1,
-                                s: f32::sqrt(
-                                    f32::abs(
-                                        sector.corners[0].0 - sector.corners[gate.own as usize].0,
-                                    )
-                                    .powi(2)
-                                        + f32::abs(
-                                            sector.corners[0].1
-                                                - sector.corners[gate.own as usize].1,
-                                        )
-                                        .powi(2),
-                                ) / (sector.floor
-                                    - cpy_sectors[gate.target_sector as usize].floor),
-                                t: 1.0,
-                                vtype: GATE,
-                            }); // 2 top left
-
-                            sector.mesh.wall_vertices.push(Vertex {
-                                x: sector.corners[gate.own as usize + 1].0,
-                                y: cpy_sectors[gate.target_sector as usize].floor,
-                                z: -1.0 * sector.corners[gate.own as usize + 1].1,
-                                s: f32::sqrt(
-                                    f32::abs(
-                                        sector.corners[0].0 - sector.corners[gate.own as usize].0,
-                                    )
-                                    .powi(2)
-                                        + f32::abs(
-                                            sector.corners[0].1
-                                                - sector.corners[gate.own as usize].1,
-                                        )
-                                        .powi(2),
-                                ) / (sector.floor
-                                    - cpy_sectors[gate.target_sector as usize].floor),
-                                t: 0.0,
-                                vtype: GATE,
-                            }); // 3 bottom left
-                        }
+// None if there is no such image
+pub fn texture_path(name: &str) -> Option<String> {
+    let path = format!("assets/textures/{name}");
+    std::path::Path::new(&path).is_file().then_some(path)
+}
 
-                        let offset = sector.mesh.wall_vertices.len() as u32 - 1;
-                        sector.mesh.wall_indices.push(offset);
-                        sector.mesh.wall_indices.push(offset - 1);
-                        sector.mesh.wall_indices.push(offset - 2);
+// At least 3 finite corners, and enough of them that every gate keeps its edge
+pub fn valid_corners(corners: &[Corner], gates: &[Gate]) -> bool {
+    corners.len() >= 3
+        && corners.iter().all(|(x, z)| x.is_finite() && z.is_finite())
+        && gates.iter().all(|gate| (gate.own as usize) < corners.len())
+}
 
-                        sector.mesh.wall_indices.push(offset - 1);
-                        sector.mesh.wall_indices.push(offset - 3);
-                        sector.mesh.wall_indices.push(offset - 2);
+// Floor and ceiling indices of the planes, for vertices with the floor and ceiling of each corner in a row
+fn triangulate_corners(corners: &[Corner]) -> Vec<u32> {
+    let corners = corners.to_vec();
+    let mut planes_indices: Vec<u32> = Vec::new();
+
+    // PLANES indices (polygon triangulation) -> Ear Clipping
+    /*
+    Rules:
+     - ccw
+     - defined in order
+     - 2 edges
+     - no intersection
+     - no holes
+     - not colinear (angle != 180°)
+     */
+    {
+        let mut index_list: Vec<(f32, f32)> = corners.clone();
+        while index_list.len() > 3 {
+            for i in 0..index_list.len() as isize {
+                // find valid ear
+                {
+                    let a = &index_list[i as usize];
+                    let b = get_item(&index_list, i + 1).unwrap();
+                    let c = get_item(&index_list, i - 1).unwrap();
+
+                    let va = (a.0 as f64, a.1 as f64);
+                    let vb = (b.0 as f64, b.1 as f64);
+                    let vc = (c.0 as f64, c.1 as f64);
+
+                    // check if angle abc is convex
+                    if orientation(va, vb, vc) == Orientation::Clockwise {
+                        continue;
                     }
 
-                    // Ceiling
-                    if sector.ceiling < cpy_sectors[gate.target_sector as usize].ceiling {
-                        sector.mesh.wall_vertices.push(Vertex {
-                            x: sector.corners[gate.own as usize].0,
-                            y: sector.ceiling,
-                            z: -1.0 * sector.corners[gate.own as usize].1,
-                            s: 0.0,
-                            t: 1.0,
-                            vtype: GATE,
-                        }); // 0 top right
-
-                        sector.mesh.wall_vertices.push(Vertex {
-                            x: sector.corners[gate.own as usize].0,
-                            y: cpy_sectors[gate.target_sector as usize].ceiling,
-                            z: -1.0 * sector.corners[gate.own as usize].1,
-                            s: 0.0,
-                            t: 0.0,
-                            vtype: GATE,
-                        }); // 1 bottom right
-
-                        // if is between last and first
-                        if gate.own as i32 == sector.corners.len() as i32 - 1 {
-                            sector.mesh.wall_vertices.push(Vertex {
-                                x: sector.corners[0].0,
-                                y: sector.ceiling,
-                                z: -1.0 * sector.corners[0].1,
-                                s: f32::sqrt(
-                                    f32::abs(
-                                        sector.corners[0].0 - sector.corners[gate.own as usize].0,
-                                    )
-                                    .powi(2)
-                                        + f32::abs(
-                                            sector.corners[0].1
-                                                - sector.corners[gate.own as usize].1,
-                                        )
-                                        .powi(2),
-                                ) / (sector.ceiling
-                                    - cpy_sectors[gate.target_sector as usize].ceiling),
-                                t: 1.0,
-                                vtype: GATE,
-                            }); // 2 top left
-
-                            sector.mesh.wall_vertices.push(Vertex {
-                                x: sector.corners[0].0,
-                                y: cpy_sectors[gate.target_sector as usize].ceiling,
-                                z: -1.0 * sector.corners[0].1,
-                                s: f32::sqrt(
-                                    f32::abs(
-                                        sector.corners[0].0 - sector.corners[gate.own as usize].0,
-                                    )
-                                    .powi(2)
-                                        + f32::abs(
-                                            sector.corners[0].1
-                                                - sector.corners[gate.own as usize].1,
-                                        )
-                                        .powi(2),
-                                ) / (sector.ceiling
-                                    - cpy_sectors[gate.target_sector as usize].ceiling),
-                                t: 0.0,
-                                vtype: GATE,
-                            }); // 3 bottom left
-                        } else {
-                            sector.mesh.wall_vertices.push(Vertex {
-                                x: sector.corners[gate.own as usize + 1].0,
-                                y: sector.ceiling,
-                                z: -1.0 * sector.corners[gate.own as usize + 1].1,
-                                s: f32::sqrt(
-                                    f32::abs(
-                                        sector.corners[0].0 - sector.corners[gate.own as usize].0,
-                                    )
-                                    .powi(2)
-                                        + f32::abs(
-                                            sector.corners[0].1
-                                                - sector.corners[gate.own as usize].1,
-                                        )
-                                        .powi(2),
-                                ) / (sector.ceiling
-                                    - cpy_sectors[gate.target_sector as usize].ceiling),
-                                t: 1.0,
-                                vtype: GATE,
-                            }); // 2 top left
-
-                            sector.mesh.wall_vertices.push(Vertex {
-                                x: sector.corners[gate.own as usize + 1].0,
-                                y: cpy_sectors[gate.target_sector as usize].ceiling,
-                                z: -1.0 * sector.corners[gate.own as usize + 1].1,
-                                s: f32::sqrt(
-                                    f32::abs(
-                                        sector.corners[0].0 - sector.corners[gate.own as usize].0,
-                                    )
-                                    .powi(2)
-                                        + f32::abs(
-                                            sector.corners[0].1
-                                                - sector.corners[gate.own as usize].1,
-                                        )
-                                        .powi(2),
-                                ) / (sector.ceiling
-                                    - cpy_sectors[gate.target_sector as usize].ceiling),
-                                t: 0.0,
-                                vtype: GATE,
-                            }); // 3 bottom left
+                    // check if other points are in triangle
+                    let mut in_triangle = false;
+                    for j in 0..index_list.len() as isize {
+                        if j == i || j == i - 1 || j == i + 1 {
+                            continue;
                         }
 
-                        let offset = sector.mesh.wall_vertices.len() as u32 - 1;
-                        sector.mesh.wall_indices.push(offset);
-                        sector.mesh.wall_indices.push(offset - 2);
-                        sector.mesh.wall_indices.push(offset - 1);
+                        let p = (
+                            index_list[j as usize].0 as f64,
+                            index_list[j as usize].1 as f64,
+                        );
+
+                        if point_in_triangle(p, va, vb, vc) {
+                            in_triangle = true;
+                            break;
+                        }
+                    }
 
-                        sector.mesh.wall_indices.push(offset - 1);
-                        sector.mesh.wall_indices.push(offset - 2);
-                        sector.mesh.wall_indices.push(offset - 3);
+                    if in_triangle {
+                        continue;
                     }
-                }
+
+                    // Add trinangles to Element Buffer
+                    let ta = index_of(&corners, a).unwrap() as u32 * 2;
+                    let tb = index_of(&corners, b).unwrap() as u32 * 2;
+                    let tc = index_of(&corners, c).unwrap() as u32 * 2;
+
+                    // floor
+                    planes_indices.push(ta);
+                    planes_indices.push(tb);
+                    planes_indices.push(tc);
+
+                    // ceiling
+                    planes_indices.push(ta + 1);
+                    planes_indices.push(tc + 1);
+                    planes_indices.push(tb + 1);
+                } // let reference run out of scope
+
+                // Remove i from Indexlist
+                index_list.remove(i as usize);
+                index_list.shrink_to_fit();
+
+                break;
             }
         }
 
-        self.create(shaders);
+        // Last triangle
+        let ta = index_of(&corners, &index_list[0]).unwrap() as u32 * 2;
+        let tb = index_of(&corners, &index_list[1]).unwrap() as u32 * 2;
+        let tc = index_of(&corners, &index_list[2]).unwrap() as u32 * 2;
+
+        // floor
+        planes_indices.push(ta);
+        planes_indices.push(tb);
+        planes_indices.push(tc);
+
+        // ceiling
+        planes_indices.push(ta + 1);
+        planes_indices.push(tc + 1);
+        planes_indices.push(tb + 1);
     }
+
+    planes_indices
 }
 
 impl RenderableShader for Map {
@@ -589,6 +475,7 @@ impl RenderableShader for Map {
 
     fn render(&self, shaders: &Shader) {
         for sector in &self.sectors {
+            shaders.set_f32("light", &sector.light);
             sector.mesh.render(shaders);
         }
     }
@@ -599,3 +486,96 @@ impl RenderableShader for Map {
         }
     }
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // A map with nothing but the sectors, json like in the map files
+    pub fn test_map(sectors: &str) -> Map {
+        let text = format!(
+            r#"{{ "name": "test", "description": "", "spawn": [1.0, 0.0, 1.0],
+                "checkpoints": [], "things": [], "sectors": {sectors} }}"#
+        );
+        let mut map = Map::new();
+        map.parse(json::parse(&text).unwrap());
+        map
+    }
+
+    // A high room with a gate on its right side into a lower one with a raised floor
+    pub fn rooms() -> Map {
+        test_map(
+            r#"[
+                { "floor": 0.0, "ceiling": 2.0, "corners": [[0, 0], [2, 0], [2, 2], [0, 2]],
+                  "gates": [{ "own": 1, "targetSector": 1, "targetGate": 3 }] },
+                { "floor": 0.5, "ceiling": 1.5, "corners": [[2, 0], [4, 0], [4, 2], [2, 2]],
+                  "gates": [{ "own": 3, "targetSector": 0, "targetGate": 1 }] }
+            ]"#,
+        )
+    }
+
+    // Map coordinates of the vertices of the sector with the type
+    fn vertices(map: &Map, sector: usize, vtype: u32) -> Vec<(f32, f32, f32)> {
+        let mesh = &map.sectors[sector].mesh;
+        let vertices = mesh.wall_vertices().iter();
+        vertices
+            .filter(|vertex| vertex.vtype == vtype)
+            .map(|vertex| (vertex.x, vertex.y, -vertex.z))
+            .collect()
+    }
+
+    // Moving the shared edge moves the walls and the steps on both sides of the gate
+    #[test]
+    fn set_corners() {
+        let mut map = rooms();
+        assert!(map.set_corners(0, vec![(0.0, 0.0), (2.5, 0.0), (2.0, 2.0), (0.0, 2.0)]));
+        assert!(map.set_corners(1, vec![(2.5, 0.0), (4.0, 0.0), (4.0, 2.0), (2.0, 2.0)]));
+
+        let walls = vertices(&map, 0, WALL);
+        assert_eq!(walls[2..4], [(2.5, 0.0, 0.0), (2.5, 2.0, 0.0)]);
+        // The lower room has the steps, up from the floor and down from the ceiling
+        assert!(vertices(&map, 0, GATE).is_empty());
+        let steps = vertices(&map, 1, GATE);
+        let expected = [
+            (2.0, 0.5, 2.0),
+            (2.0, 0.0, 2.0),
+            (2.5, 0.5, 0.0),
+            (2.5, 0.0, 0.0),
+            (2.0, 1.5, 2.0),
+            (2.0, 2.0, 2.0),
+            (2.5, 1.5, 0.0),
+            (2.5, 2.0, 0.0),
+        ];
+        assert_eq!(steps, expected);
+        assert_eq!(map.sector_at(2.3, 0.5), Some(0));
+    }
+
+    // Raising the floor of one room moves the step over to it
+    #[test]
+    fn set_heights() {
+        let mut map = rooms();
+        map.set_heights(0, 1.0, 2.0);
+        let steps = vertices(&map, 0, GATE);
+        assert_eq!(steps[0], (2.0, 1.0, 0.0));
+        assert_eq!(steps[1], (2.0, 0.5, 0.0));
+        assert_eq!(vertices(&map, 1, GATE).len(), 4);
+    }
+
+    #[test]
+    fn invalid_corners() {
+        let mut map = rooms();
+        assert!(!map.set_corners(0, vec![(0.0, 0.0), (2.0, 0.0)]));
+        assert!(!map.set_corners(0, vec![(0.0, 0.0), (2.0, f32::NAN), (0.0, 2.0)]));
+        // The gate is on the fourth edge
+        assert!(!map.set_corners(1, vec![(2.0, 0.0), (4.0, 0.0), (4.0, 2.0)]));
+        assert_eq!(map.sectors[1].corners.len(), 4);
+    }
+
+    #[test]
+    fn set_texture() {
+        let mut map = rooms();
+        assert!(map.set_texture(0, FLOOR, "fallback.png"));
+        assert!(!map.set_texture(0, FLOOR, "missing.png"));
+        assert!(!map.set_texture(0, 0, "fallback.png"));
+    }
+}
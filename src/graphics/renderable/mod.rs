@@ -1,9 +1,12 @@
 use image::io::Reader as ImageReader;
 use nalgebra_glm as ng;
 
+pub mod grid;
 pub mod gui;
 pub mod mapdata;
+pub mod raycast;
 pub mod sector;
+pub mod sprites;
 
 pub trait Renderable {
     fn create(&mut self);
@@ -0,0 +1,273 @@
+use super::mapdata::Map;
+use crate::geometry::{Segment, EPSILON};
+use nalgebra_glm as ng;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HitKind {
+    Wall,
+    Floor,
+    Ceiling,
+    Step, // part of a gate above or below the opening into the next sector
+}
+
+// Everything in map coordinates (x, y, z as in the map file)
+#[derive(Clone, Copy, Debug)]
+pub struct RayHit {
+    pub kind: HitKind,
+    pub point: ng::DVec3,
+    pub normal: ng::DVec3, // points to the side the ray came from
+    pub distance: f64,
+    pub sector: u32,       // the ray was in when it hit
+    pub edge: Option<u32>, // of that sector, None for floor and ceiling
+}
+
+// Result of a line of sight check, map coordinates
+#[derive(Clone, Debug)]
+pub struct Sight {
+    pub visible: bool,
+    pub sectors: Vec<u32>, // passed through in order, up to where the line got blocked
+}
+
+impl Map {
+    // Walks from the sector of the origin through gates, returns the first hit within max_dist
+    pub fn raycast(
+        &self,
+        origin: ng::DVec3,
+        direction: ng::DVec3,
+        max_dist: f64,
+    ) -> Option<RayHit> {
+        self.trace(origin, direction, max_dist, None)
+    }
+
+    // Eye to eye, blocked by walls, floors, ceilings and the steps of gates
+    pub fn line_of_sight(&self, from: ng::DVec3, to: ng::DVec3) -> Sight {
+        let mut sectors = Vec::new();
+        let distance = ng::distance(&from, &to);
+        let visible = if distance <= EPSILON {
+            self.sector_at(from.x as f32, from.z as f32)
+                .map(|sector| sectors.push(sector))
+                .is_some()
+        } else {
+            self.trace(from, to - from, distance, Some(&mut sectors))
+                .is_none()
+                && !sectors.is_empty()
+        };
+        Sight { visible, sectors }
+    }
+
+    // Collects the sectors passed through if there is somewhere to put them
+    fn trace(
+        &self,
+        origin: ng::DVec3,
+        direction: ng::DVec3,
+        max_dist: f64,
+        mut sectors: Option<&mut Vec<u32>>,
+    ) -> Option<RayHit> {
+        if max_dist <= 0.0 || ng::length(&direction) <= EPSILON {
+            return None;
+        }
+
+        let end = origin + ng::normalize(&direction) * max_dist;
+        let at = |t: f64| origin + (end - origin) * t;
+        let ray = Segment::new((origin.x, origin.z), (end.x, end.z));
+        let ray_xz = (end.x - origin.x, end.z - origin.z);
+
+        let mut sector_index = self.sector_at(origin.x as f32, origin.z as f32)?;
+        let mut entry: Option<usize> = None; // edge the ray came in through
+        let mut t_enter = 0.0;
+
+        // Every gate can only be passed a few times by a straight ray, this just guards against broken maps
+        for _ in 0..self.sectors.len() * 4 + 4 {
+            if let Some(sectors) = &mut sectors {
+                sectors.push(sector_index);
+            }
+            let sector = &self.sectors[sector_index as usize];
+            let is_gate = |edge: usize| sector.gates.iter().any(|gate| gate.own as usize == edge);
+
+            // Find the edge the ray leaves through, walls win over gates at the same spot (corners)
+            let mut exit: Option<(usize, f64)> = None;
+            if ray.length() > EPSILON {
+                for i in 0..sector.corners.len() {
+                    if entry == Some(i) {
+                        continue;
+                    }
+
+                    let corner0 = sector.corners[i];
+                    let corner1 = sector.corners[(i + 1) % sector.corners.len()];
+                    let edge = Segment::new(
+                        (corner0.0 as f64, corner0.1 as f64),
+                        (corner1.0 as f64, corner1.1 as f64),
+                    );
+
+                    // Only edges which are crossed from the inside to the outside
+                    let edge_xz = (edge.b.0 - edge.a.0, edge.b.1 - edge.a.1);
+                    if edge_xz.0 * ray_xz.1 - edge_xz.1 * ray_xz.0 >= 0.0 {
+                        continue;
+                    }
+
+                    let Some(hit) = ray.intersect(&edge) else {
+                        continue;
+                    };
+                    if hit.t < t_enter - EPSILON {
+                        continue;
+                    }
+
+                    let earlier = match exit {
+                        None => true,
+                        Some((other, t)) => {
+                            hit.t < t - EPSILON
+                                || (hit.t <= t + EPSILON && is_gate(other) && !is_gate(i))
+                        }
+                    };
+                    if earlier {
+                        exit = Some((i, hit.t));
+                    }
+                }
+            }
+
+            // Floor and ceiling between entering and leaving the sector
+            let t_exit = exit.map_or(1.0, |(_, t)| t);
+            let y_exit = at(t_exit).y;
+            let plane = if y_exit < sector.floor as f64 {
+                Some((HitKind::Floor, sector.floor as f64, 1.0))
+            } else if y_exit > sector.ceiling as f64 {
+                Some((HitKind::Ceiling, sector.ceiling as f64, -1.0))
+            } else {
+                None
+            };
+            if let Some((kind, y, normal)) = plane {
+                let t = ((y - origin.y) / (end.y - origin.y)).clamp(t_enter, t_exit);
+                return Some(RayHit {
+                    kind,
+                    point: at(t),
+                    normal: ng::DVec3::new(0.0, normal, 0.0),
+                    distance: t * max_dist,
+                    sector: sector_index,
+                    edge: None,
+                });
+            }
+
+            let (edge, t) = exit?;
+            let corner0 = sector.corners[edge];
+            let corner1 = sector.corners[(edge + 1) % sector.corners.len()];
+            // The inside of a ccw sector is on the left of its edges
+            let normal = ng::normalize(&ng::DVec3::new(
+                (corner0.1 - corner1.1) as f64,
+                0.0,
+                (corner1.0 - corner0.0) as f64,
+            ));
+            let hit = RayHit {
+                kind: HitKind::Wall,
+                point: at(t),
+                normal,
+                distance: t * max_dist,
+                sector: sector_index,
+                edge: Some(edge as u32),
+            };
+
+            let Some(gate) = sector.gates.iter().find(|gate| gate.own as usize == edge) else {
+                return Some(hit);
+            };
+
+            let target = &self.sectors[gate.target_sector as usize];
+            if hit.point.y < target.floor as f64 || hit.point.y > target.ceiling as f64 {
+                return Some(RayHit {
+                    kind: HitKind::Step,
+                    ..hit
+                });
+            }
+
+            sector_index = gate.target_sector;
+            entry = Some(gate.target_gate as usize);
+            t_enter = t;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::renderable::mapdata::tests::rooms;
+
+    fn cast(map: &Map, origin: (f64, f64, f64), direction: (f64, f64, f64)) -> Option<RayHit> {
+        map.raycast(
+            ng::DVec3::new(origin.0, origin.1, origin.2),
+            ng::DVec3::new(direction.0, direction.1, direction.2),
+            10.0,
+        )
+    }
+
+    fn assert_hit(hit: RayHit, kind: HitKind, point: (f64, f64, f64), normal: (f64, f64, f64)) {
+        assert_eq!(hit.kind, kind);
+        assert!(ng::distance(&hit.point, &ng::DVec3::new(point.0, point.1, point.2)) < 1e-6);
+        assert!(ng::distance(&hit.normal, &ng::DVec3::new(normal.0, normal.1, normal.2)) < 1e-6);
+    }
+
+    #[test]
+    fn wall() {
+        let hit = cast(&rooms(), (1.0, 1.0, 1.0), (-1.0, 0.0, 0.0)).unwrap();
+        assert_hit(hit, HitKind::Wall, (0.0, 1.0, 1.0), (1.0, 0.0, 0.0));
+        assert_eq!((hit.sector, hit.edge), (0, Some(3)));
+        assert!((hit.distance - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn through_gate() {
+        let hit = cast(&rooms(), (1.0, 1.0, 1.0), (1.0, 0.0, 0.0)).unwrap();
+        assert_hit(hit, HitKind::Wall, (4.0, 1.0, 1.0), (-1.0, 0.0, 0.0));
+        assert_eq!((hit.sector, hit.edge), (1, Some(1)));
+    }
+
+    #[test]
+    fn floor_and_ceiling() {
+        let hit = cast(&rooms(), (1.0, 1.0, 1.0), (0.0, -1.0, 0.0)).unwrap();
+        assert_hit(hit, HitKind::Floor, (1.0, 0.0, 1.0), (0.0, 1.0, 0.0));
+        assert_eq!((hit.sector, hit.edge), (0, None));
+
+        let hit = cast(&rooms(), (1.0, 1.0, 1.0), (0.0, 1.0, 0.0)).unwrap();
+        assert_hit(hit, HitKind::Ceiling, (1.0, 2.0, 1.0), (0.0, -1.0, 0.0));
+        assert_eq!((hit.sector, hit.edge), (0, None));
+    }
+
+    #[test]
+    fn step_up() {
+        let hit = cast(&rooms(), (1.0, 0.25, 1.0), (1.0, 0.0, 0.0)).unwrap();
+        assert_hit(hit, HitKind::Step, (2.0, 0.25, 1.0), (-1.0, 0.0, 0.0));
+        assert_eq!((hit.sector, hit.edge), (0, Some(1)));
+
+        // Above the lower ceiling of the next room
+        let hit = cast(&rooms(), (1.0, 1.75, 1.0), (1.0, 0.0, 0.0)).unwrap();
+        assert_eq!(hit.kind, HitKind::Step);
+    }
+
+    #[test]
+    fn miss() {
+        let map = rooms();
+        let short = map.raycast(
+            ng::DVec3::new(1.0, 1.0, 1.0),
+            ng::DVec3::new(1.0, 0.0, 0.0),
+            2.0,
+        );
+        assert!(short.is_none());
+        assert!(cast(&map, (5.0, 1.0, 1.0), (1.0, 0.0, 0.0)).is_none());
+        assert!(cast(&map, (1.0, 1.0, 1.0), (0.0, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn sight_blocked_by_step() {
+        let from = ng::DVec3::new(1.0, 0.1, 1.0);
+        let sight = rooms().line_of_sight(from, ng::DVec3::new(3.0, 0.6, 1.0));
+        assert!(!sight.visible);
+        assert_eq!(sight.sectors, vec![0]);
+    }
+
+    #[test]
+    fn clear_sight() {
+        let from = ng::DVec3::new(1.0, 1.0, 1.0);
+        let sight = rooms().line_of_sight(from, ng::DVec3::new(3.0, 1.0, 1.5));
+        assert!(sight.visible);
+        assert_eq!(sight.sectors, vec![0, 1]);
+    }
+}
@@ -19,15 +19,63 @@ pub struct Vertex {
 
 #[derive(Clone)]
 pub struct Sector {
-    wall_model: Model,
-    pub wall_vertices: Vec<Vertex>,
-    pub wall_indices: Vec<u32>,
+    walls: Mesh,
+    planes: Mesh,
+    texture: TextureData,
+    changed_textures: Vec<u32>, // kinds to load again
+}
 
-    planes_model: Model,
-    pub planes_vertices: Vec<Vertex>,
-    pub planes_indices: Vec<u32>,
+// Vertices and indices with the buffers they go into
+#[derive(Clone, Default)]
+struct Mesh {
+    model: Model,
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    sizes: (isize, isize), // bytes on the GPU, vertices and indices
+    dirty: (bool, bool),   // changed since the last upload, vertices and indices
+}
 
-    texture: TextureData,
+impl Mesh {
+    // Indices stay the same with None
+    fn set(&mut self, vertices: Vec<Vertex>, indices: Option<Vec<u32>>) {
+        self.vertices = vertices;
+        self.dirty.0 = true;
+        if let Some(indices) = indices {
+            if indices != self.indices {
+                self.indices = indices;
+                self.dirty.1 = true;
+            }
+        }
+    }
+
+    fn upload(&mut self) {
+        if self.model.vao == 0 {
+            return;
+        }
+        unsafe {
+            // The element buffer belongs to the vertex array
+            gl::BindVertexArray(self.model.vao);
+            if self.dirty.0 {
+                upload_buffer(
+                    gl::ARRAY_BUFFER,
+                    self.model.vbo,
+                    &self.vertices,
+                    &mut self.sizes.0,
+                );
+            }
+            if self.dirty.1 {
+                upload_buffer(
+                    gl::ELEMENT_ARRAY_BUFFER,
+                    self.model.ebo,
+                    &self.indices,
+                    &mut self.sizes.1,
+                );
+            }
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+        self.dirty = (false, false);
+    }
 }
 
 #[derive(Clone)]
@@ -38,24 +86,72 @@ pub struct TextureData {
     pub gate: (String, u32),
 }
 
+impl TextureData {
+    // By the vertex types, FLOOR, CEILING, WALL or GATE
+    fn get_mut(&mut self, kind: u32) -> Option<&mut (String, u32)> {
+        match kind {
+            FLOOR => Some(&mut self.floor),
+            CEILING => Some(&mut self.ceiling),
+            WALL => Some(&mut self.wall),
+            GATE => Some(&mut self.gate),
+            _ => None,
+        }
+    }
+}
+
 impl Sector {
-    pub fn new(
+    // The geometry comes later with set_geometry
+    pub fn new(texture: TextureData) -> Self {
+        Sector {
+            walls: Mesh::default(),
+            planes: Mesh::default(),
+            texture,
+            changed_textures: Vec::new(),
+        }
+    }
+
+    // Replaces the geometry, it gets sent to the GPU with the next upload.
+    // The triangulation of the planes stays the same with None
+    pub fn set_geometry(
+        &mut self,
         wall_vertices: Vec<Vertex>,
         wall_indices: Vec<u32>,
         planes_vertices: Vec<Vertex>,
-        planes_indices: Vec<u32>,
-        texture: TextureData,
-    ) -> Self {
-        Sector {
-            wall_model: Model::default(),
-            wall_vertices,
-            wall_indices,
+        planes_indices: Option<Vec<u32>>,
+    ) {
+        self.walls.set(wall_vertices, Some(wall_indices));
+        self.planes.set(planes_vertices, planes_indices);
+    }
 
-            planes_model: Model::default(),
-            planes_vertices,
-            planes_indices,
+    // Kind is FLOOR, CEILING, WALL or GATE, returns false for anything else
+    pub fn set_texture(&mut self, kind: u32, path: String) -> bool {
+        let Some(texture) = self.texture.get_mut(kind) else {
+            return false;
+        };
+        texture.0 = path;
+        if !self.changed_textures.contains(&kind) {
+            self.changed_textures.push(kind);
+        }
+        true
+    }
 
-            texture,
+    #[cfg(test)]
+    pub fn wall_vertices(&self) -> &[Vertex] {
+        &self.walls.vertices
+    }
+
+    // Sends changed geometry and textures to the GPU, does nothing before create
+    pub fn upload(&mut self) {
+        if self.walls.model.vao == 0 {
+            return;
+        }
+        self.walls.upload();
+        self.planes.upload();
+
+        for kind in std::mem::take(&mut self.changed_textures) {
+            if let Some((path, id)) = self.texture.get_mut(kind) {
+                create_texture(*id, path);
+            }
         }
     }
 
@@ -69,133 +165,71 @@ impl Sector {
     }
 }
 
+// The buffers are dynamic since doors and lifts change them while playing,
+// they only get replaced when the size changes
+unsafe fn upload_buffer<T>(target: u32, buffer: u32, data: &[T], size: &mut isize) {
+    let bytes = std::mem::size_of_val(data) as isize;
+    gl::BindBuffer(target, buffer);
+    if bytes == *size {
+        gl::BufferSubData(target, 0, bytes, data.as_ptr().cast());
+    } else {
+        gl::BufferData(target, bytes, data.as_ptr().cast(), gl::DYNAMIC_DRAW);
+        *size = bytes;
+    }
+}
+
 impl RenderableShader for Sector {
     fn create(&mut self, shaders: &Shader) {
         unsafe {
-            // WALLS
-            gl::GenVertexArrays(1, &mut self.wall_model.vao);
-            gl::GenBuffers(1, &mut self.wall_model.vbo);
-            gl::GenBuffers(1, &mut self.wall_model.ebo);
-
-            gl::BindVertexArray(self.wall_model.vao);
-
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.wall_model.vbo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                self.wall_vertices
-                    .as_ptr_range()
-                    .end
-                    .offset_from(self.wall_vertices.as_ptr_range().start)
-                    * std::mem::size_of::<Vertex>() as isize,
-                self.wall_vertices.as_ptr().cast(),
-                gl::STATIC_DRAW,
-            );
-
-            gl::VertexAttribPointer(
-                0,
-                3,
-                gl::FLOAT,
-                gl::FALSE,
-                std::mem::size_of::<Vertex>().try_into().unwrap(),
-                0 as *const _,
-            );
-            gl::EnableVertexAttribArray(0);
-
-            gl::VertexAttribPointer(
-                1,
-                2,
-                gl::FLOAT,
-                gl::FALSE,
-                std::mem::size_of::<Vertex>().try_into().unwrap(),
-                (std::mem::size_of::<f32>() * 3) as *const _,
-            );
-            gl::EnableVertexAttribArray(1);
-
-            gl::VertexAttribIPointer(
-                3,
-                1,
-                gl::UNSIGNED_INT,
-                std::mem::size_of::<Vertex>().try_into().unwrap(),
-                (std::mem::size_of::<f32>() * 5) as *const _,
-            );
-            gl::EnableVertexAttribArray(3);
-
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.wall_model.ebo);
-            gl::BufferData(
-                gl::ELEMENT_ARRAY_BUFFER,
-                self.wall_indices
-                    .as_ptr_range()
-                    .end
-                    .offset_from(self.wall_indices.as_ptr_range().start)
-                    * std::mem::size_of::<u32>() as isize,
-                self.wall_indices.as_ptr().cast(),
-                gl::STATIC_DRAW,
-            );
-
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-
-            // PLANES
-            gl::GenVertexArrays(1, &mut self.planes_model.vao);
-            gl::GenBuffers(1, &mut self.planes_model.vbo);
-            gl::GenBuffers(1, &mut self.planes_model.ebo);
-
-            gl::BindVertexArray(self.planes_model.vao);
-
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.planes_model.vbo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                self.planes_vertices
-                    .as_ptr_range()
-                    .end
-                    .offset_from(self.planes_vertices.as_ptr_range().start)
-                    * std::mem::size_of::<Vertex>() as isize,
-                self.planes_vertices.as_ptr().cast(),
-                gl::STATIC_DRAW,
-            );
-
-            gl::VertexAttribPointer(
-                0,
-                3,
-                gl::FLOAT,
-                gl::FALSE,
-                std::mem::size_of::<Vertex>().try_into().unwrap(),
-                0 as *const _,
-            );
-            gl::EnableVertexAttribArray(0);
-
-            gl::VertexAttribPointer(
-                1,
-                2,
-                gl::FLOAT,
-                gl::FALSE,
-                std::mem::size_of::<Vertex>().try_into().unwrap(),
-                (std::mem::size_of::<f32>() * 3) as *const _,
-            );
-            gl::EnableVertexAttribArray(1);
-
-            gl::VertexAttribIPointer(
-                3,
-                1,
-                gl::UNSIGNED_INT,
-                std::mem::size_of::<Vertex>().try_into().unwrap(),
-                (std::mem::size_of::<f32>() * 5) as *const _,
-            );
-            gl::EnableVertexAttribArray(3);
-
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.planes_model.ebo);
-            gl::BufferData(
-                gl::ELEMENT_ARRAY_BUFFER,
-                self.planes_indices
-                    .as_ptr_range()
-                    .end
-                    .offset_from(self.planes_indices.as_ptr_range().start)
-                    * std::mem::size_of::<u32>() as isize,
-                self.planes_indices.as_ptr().cast(),
-                gl::STATIC_DRAW,
-            );
-
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            for model in [&mut self.walls.model, &mut self.planes.model] {
+                gl::GenVertexArrays(1, &mut model.vao);
+                gl::GenBuffers(1, &mut model.vbo);
+                gl::GenBuffers(1, &mut model.ebo);
+
+                gl::BindVertexArray(model.vao);
+                gl::BindBuffer(gl::ARRAY_BUFFER, model.vbo);
+
+                gl::VertexAttribPointer(
+                    0,
+                    3,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    std::mem::size_of::<Vertex>().try_into().unwrap(),
+                    std::ptr::null(),
+                );
+                gl::EnableVertexAttribArray(0);
+
+                gl::VertexAttribPointer(
+                    1,
+                    2,
+                    gl::FLOAT,
+                    gl::FALSE,
+                    std::mem::size_of::<Vertex>().try_into().unwrap(),
+                    (std::mem::size_of::<f32>() * 3) as *const _,
+                );
+                gl::EnableVertexAttribArray(1);
+
+                gl::VertexAttribIPointer(
+                    3,
+                    1,
+                    gl::UNSIGNED_INT,
+                    std::mem::size_of::<Vertex>().try_into().unwrap(),
+                    (std::mem::size_of::<f32>() * 5) as *const _,
+                );
+                gl::EnableVertexAttribArray(3);
+
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, model.ebo);
+                gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            }
+        }
+        for mesh in [&mut self.walls, &mut self.planes] {
+            mesh.sizes = (0, 0);
+            mesh.dirty = (true, true);
+        }
+        self.changed_textures.clear();
+        self.upload();
 
+        unsafe {
             shaders.use_program();
 
             gl::GenTextures(1, &mut self.texture.floor.1);
@@ -229,11 +263,11 @@ impl RenderableShader for Sector {
             gl::BindTexture(gl::TEXTURE_2D, self.texture.gate.1);
         }
 
-        self.draw(shaders, &self.wall_model, self.wall_indices.len() as i32);
+        self.draw(shaders, &self.walls.model, self.walls.indices.len() as i32);
         self.draw(
             shaders,
-            &self.planes_model,
-            self.planes_indices.len() as i32,
+            &self.planes.model,
+            self.planes.indices.len() as i32,
         );
     }
 
@@ -0,0 +1,235 @@
+use super::{create_texture, Model};
+use crate::graphics::shader::Shader;
+use nalgebra_glm as ng;
+use std::collections::HashMap;
+
+pub const SPRITE_DIR: &str = "assets/sprites";
+
+const PIXELS_PER_UNIT: f32 = 64.0;
+const OVERLAY_PIXELS: f32 = 200.0; // screen height in sprite pixels, like Doom
+
+// One image of a frame, as seen from one side
+#[derive(Clone, Copy, Debug)]
+struct Rotation {
+    texture: u32,
+    mirrored: bool,
+    size: (f32, f32), // width, height in units
+}
+
+// Something to draw this frame, world coordinates
+pub struct Sprite {
+    pub frame: String,      // like TROOA, the rotation gets picked when drawing
+    pub position: ng::Vec3, // bottom center
+    pub angle: f32,         // map degrees the actor faces
+    pub light: f32,         // of the sector it is in
+}
+
+// Picks 1 to 8 like Doom, 1 looks at the viewer and 5 away from it.
+// Angles in map degrees, 0 looks along x and 90 along z
+fn rotation(facing: f32, to_viewer: f32) -> usize {
+    ((to_viewer - facing + 22.5).rem_euclid(360.0) / 45.0) as usize % 8 + 1
+}
+
+// Doom names: four letters, the frame and the rotation like TROOA1, 0 is the same from every side.
+// A second frame and rotation reuses the image mirrored, TROOA2A8 is A2 as is and A8 flipped
+fn parse_name(name: &str) -> Vec<(String, usize, bool)> {
+    let bytes = name.as_bytes();
+    let mut parsed = Vec::new();
+    for (offset, mirrored) in [(4, false), (6, true)] {
+        if bytes.len() < offset + 2 || (mirrored && bytes.len() != 8) {
+            break;
+        }
+        let rotation = (bytes[offset + 1] as char).to_digit(10);
+        if !bytes[offset].is_ascii_alphabetic() || rotation.is_none_or(|r| r > 8) {
+            break;
+        }
+        let frame = format!("{}{}", &name[..4], bytes[offset] as char);
+        parsed.push((frame, rotation.unwrap() as usize, mirrored));
+    }
+    parsed
+}
+
+// Camera facing quads for the actors, frames by name
+pub struct SpriteRenderer {
+    quad: Model,
+    frames: HashMap<String, [Option<Rotation>; 9]>, // by rotation, 0 for all sides
+}
+
+impl SpriteRenderer {
+    // Loads every image in the directory, needs the OpenGL context
+    pub fn load(dir: &str) -> Self {
+        let mut renderer = SpriteRenderer {
+            quad: Model::default(),
+            frames: HashMap::new(),
+        };
+        renderer.create_quad();
+
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            println!("Could not find sprite directory '{dir}'");
+            return renderer;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|extension| extension != "png") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let parsed = parse_name(name);
+            if parsed.is_empty() {
+                println!("Invalid sprite name '{name}'");
+                continue;
+            }
+
+            let Ok((width, height)) = image::image_dimensions(&path) else {
+                println!("Could not read sprite '{}'", path.display());
+                continue;
+            };
+            let mut texture = 0;
+            unsafe {
+                gl::GenTextures(1, &mut texture);
+            }
+            create_texture(texture, path.to_str().unwrap());
+            unsafe {
+                // Keeps the edges from bleeding into the other side
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            }
+
+            for (frame, rotation, mirrored) in parsed {
+                renderer.frames.entry(frame).or_default()[rotation] = Some(Rotation {
+                    texture,
+                    mirrored,
+                    size: (
+                        width as f32 / PIXELS_PER_UNIT,
+                        height as f32 / PIXELS_PER_UNIT,
+                    ),
+                });
+            }
+        }
+        renderer
+    }
+
+    fn create_quad(&mut self) {
+        // x from the center, y from the bottom
+        let vertices: [f32; 8] = [-0.5, 0.0, 0.5, 0.0, 0.5, 1.0, -0.5, 1.0];
+        let indices: [u32; 6] = [0, 1, 2, 0, 2, 3];
+        unsafe {
+            gl::GenVertexArrays(1, &mut self.quad.vao);
+            gl::GenBuffers(1, &mut self.quad.vbo);
+            gl::GenBuffers(1, &mut self.quad.ebo);
+
+            gl::BindVertexArray(self.quad.vao);
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.quad.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                std::mem::size_of_val(&vertices) as isize,
+                vertices.as_ptr().cast(),
+                gl::STATIC_DRAW,
+            );
+
+            gl::VertexAttribPointer(
+                0,
+                2,
+                gl::FLOAT,
+                gl::FALSE,
+                (std::mem::size_of::<f32>() * 2) as i32,
+                std::ptr::null(),
+            );
+            gl::EnableVertexAttribArray(0);
+
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.quad.ebo);
+            gl::BufferData(
+                gl::ELEMENT_ARRAY_BUFFER,
+                std::mem::size_of_val(&indices) as isize,
+                indices.as_ptr().cast(),
+                gl::STATIC_DRAW,
+            );
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+    }
+
+    fn find(&self, sprite: &Sprite, camera: &ng::Vec3) -> Option<Rotation> {
+        let rotations = self.frames.get(&sprite.frame)?;
+        if let Some(all) = rotations[0] {
+            return Some(all);
+        }
+
+        // The camera in map coordinates, z is flipped
+        let to_viewer = (-camera.z + sprite.position.z).atan2(camera.x - sprite.position.x);
+        rotations[rotation(sprite.angle, to_viewer.to_degrees())]
+    }
+
+    // Flat on the screen without depth, position is the bottom center from -1 to 1.
+    // Aspect is the width of the screen by its height
+    pub fn render_overlay(
+        &self,
+        shaders: &Shader,
+        frame: &str,
+        position: ng::Vec2,
+        aspect: f32,
+        light: f32,
+    ) {
+        let Some(rotation) = self.frames.get(frame).and_then(|rotations| rotations[0]) else {
+            return;
+        };
+
+        let scale = PIXELS_PER_UNIT / OVERLAY_PIXELS * 2.0;
+        shaders.set_mat4("projection", &ng::Mat4::identity());
+        shaders.set_mat4("view", &ng::Mat4::identity());
+        shaders.set_vec3("position", &ng::vec3(position.x, position.y, 0.0));
+        shaders.set_vec3("right", &ng::vec3(1.0, 0.0, 0.0));
+        shaders.set_vec2(
+            "size",
+            &ng::vec2(rotation.size.0 * scale / aspect, rotation.size.1 * scale),
+        );
+        shaders.set_i32("bMirrored", &0);
+        shaders.set_f32("light", &light);
+        shaders.set_i32("tx_sprite", &0);
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindVertexArray(self.quad.vao);
+            gl::BindTexture(gl::TEXTURE_2D, rotation.texture);
+            gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, 0 as _);
+        }
+    }
+
+    // Expects the shader to be in use with the matrices set, the map has to be drawn first for the depth
+    pub fn render(&self, shaders: &Shader, sprites: &[Sprite], camera: &ng::Vec3) {
+        shaders.set_i32("tx_sprite", &0);
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindVertexArray(self.quad.vao);
+        }
+
+        for sprite in sprites {
+            let Some(rotation) = self.find(sprite, camera) else {
+                continue;
+            };
+
+            // Only turns around the up axis so the sprites stay upright
+            let forward = ng::vec3(
+                sprite.position.x - camera.x,
+                0.0,
+                sprite.position.z - camera.z,
+            );
+            if ng::length(&forward) <= f32::EPSILON {
+                continue;
+            }
+            let right = ng::normalize(&ng::cross(&forward, &ng::vec3(0.0, 1.0, 0.0)));
+
+            shaders.set_vec3("position", &sprite.position);
+            shaders.set_vec3("right", &right);
+            shaders.set_vec2("size", &ng::vec2(rotation.size.0, rotation.size.1));
+            shaders.set_i32("bMirrored", &(rotation.mirrored as i32));
+            shaders.set_f32("light", &sprite.light);
+            unsafe {
+                gl::BindTexture(gl::TEXTURE_2D, rotation.texture);
+                gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, 0 as _);
+            }
+        }
+    }
+}
@@ -82,6 +82,38 @@ impl Shader {
             );
         }
     }
+
+    pub fn set_f32(&self, name: &str, value: &f32) {
+        let cname = std::ffi::CString::new(name).expect("CString::new failed.");
+        unsafe {
+            gl::Uniform1f(
+                gl::GetUniformLocation(self.id, cname.as_ptr().cast()),
+                *value,
+            );
+        }
+    }
+
+    pub fn set_vec2(&self, name: &str, value: &nalgebra_glm::Vec2) {
+        let cname = std::ffi::CString::new(name).expect("CString::new failed.");
+        unsafe {
+            gl::Uniform2fv(
+                gl::GetUniformLocation(self.id, cname.as_ptr().cast()),
+                1,
+                value.as_ptr(),
+            );
+        }
+    }
+
+    pub fn set_vec3(&self, name: &str, value: &nalgebra_glm::Vec3) {
+        let cname = std::ffi::CString::new(name).expect("CString::new failed.");
+        unsafe {
+            gl::Uniform3fv(
+                gl::GetUniformLocation(self.id, cname.as_ptr().cast()),
+                1,
+                value.as_ptr(),
+            );
+        }
+    }
 }
 
 fn check_for_error(shader: &u32, shader_type: &str) {
@@ -0,0 +1,421 @@
+use crate::demo::Playback;
+use crate::graphics::renderable::mapdata::Map;
+use crate::input::{Action, TickCommand};
+use crate::navigation::{Clearance, Pathfinder};
+use crate::player::{PLAYER_HEIGHT, PLAYER_RADIUS};
+use crate::world::{World, WorldState};
+
+pub enum Source {
+    Idle(u64), // number of ticks without input
+    Script(Vec<TickCommand>),
+    Demo(Playback),
+}
+
+// Runs the simulation without a window, returns the state after the last tick
+pub fn run(world: &mut World, source: Source, trace: bool) -> WorldState {
+    let mut playback = None;
+    let commands = match source {
+        Source::Idle(ticks) => vec![TickCommand::default(); ticks as usize],
+        Source::Script(commands) => commands,
+        Source::Demo(demo) => {
+            playback = Some(demo);
+            Vec::new()
+        }
+    };
+    let mut commands = commands.into_iter();
+
+    loop {
+        let cmd = match &mut playback {
+            Some(playback) => playback.next_command(),
+            None => commands.next(),
+        };
+        let Some(cmd) = cmd else {
+            break;
+        };
+
+        world.tick(&cmd);
+
+        let state = world.state();
+        if let Some(playback) = &mut playback {
+            playback.check(state.into());
+        }
+        if trace {
+            println!("{state}");
+            for event in world.events() {
+                println!("  {event}");
+            }
+        }
+    }
+
+    if let Some(playback) = &playback {
+        match playback.desync() {
+            Some(tick) => println!("Demo desynced at tick {tick}"),
+            None => println!("Demo finished in sync"),
+        }
+    }
+
+    world.state()
+}
+
+// Reports sectors and things the player can not walk to from the spawn, returns how many
+pub fn check_reachability(map: &Map) -> usize {
+    let clearance = Clearance {
+        height: PLAYER_HEIGHT,
+        max_step: f32::INFINITY,
+        radius: PLAYER_RADIUS,
+    };
+    let spawn = (map.spawn.0 as f64, map.spawn.2 as f64);
+    let mut paths = Pathfinder::default();
+    let mut unreachable = 0;
+
+    for (i, sector) in map.sectors.iter().enumerate() {
+        // A point inside of the sector, the center or a corner pulled a bit towards it
+        let count = sector.corners.len() as f64;
+        let center = sector.corners.iter().fold((0.0, 0.0), |sum, corner| {
+            (
+                sum.0 + corner.0 as f64 / count,
+                sum.1 + corner.1 as f64 / count,
+            )
+        });
+        let inside = std::iter::once(center)
+            .chain(sector.corners.iter().map(|corner| {
+                let corner = (corner.0 as f64, corner.1 as f64);
+                (
+                    corner.0 + (center.0 - corner.0) * 0.01,
+                    corner.1 + (center.1 - corner.1) * 0.01,
+                )
+            }))
+            .find(|point| map.sector_at(point.0 as f32, point.1 as f32) == Some(i as u32));
+        let Some(point) = inside else {
+            continue;
+        };
+
+        if !paths.reachable(map, spawn, point, clearance) {
+            println!("Sector {i} is unreachable");
+            unreachable += 1;
+        }
+    }
+
+    for (i, thing) in map.things.iter().enumerate() {
+        let point = (thing.position.0 as f64, thing.position.2 as f64);
+        if !paths.reachable(map, spawn, point, clearance) {
+            println!("Thing {i} ({:?}) is unreachable", thing.kind);
+            unreachable += 1;
+        }
+    }
+
+    println!("{unreachable} unreachable in '{}'", map.path);
+    unreachable
+}
+
+/*
+Script format, one command per line, '#' starts a comment:
+ - wait <ticks>
+ - forward|backward|left|right <ticks>
+ - turn <degrees>         (yaw in one tick)
+ - look <degrees>         (pitch in one tick)
+ - press <action>         (one tick, e.g. "press toggle_spectator")
+ - hold <action> <ticks>
+ */
+pub fn parse_script(text: &str) -> Result<Vec<TickCommand>, String> {
+    let mut commands = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let error = |message: &str| format!("line {}: {message} '{line}'", number + 1);
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number_at = |i: usize| -> Result<f64, String> {
+            words
+                .get(i)
+                .and_then(|word| word.parse::<f64>().ok())
+                .ok_or_else(|| error("expected a number in"))
+        };
+        let action_at = |i: usize| -> Result<Action, String> {
+            words
+                .get(i)
+                .and_then(|word| Action::from_name(word))
+                .ok_or_else(|| error("expected an action in"))
+        };
+        let repeat = |commands: &mut Vec<TickCommand>, cmd: TickCommand, ticks: f64| {
+            for _ in 0..ticks as usize {
+                commands.push(cmd);
+            }
+        };
+
+        let held = |action: Action| TickCommand {
+            held: action.bit(),
+            ..Default::default()
+        };
+
+        match words[0] {
+            "wait" => repeat(&mut commands, TickCommand::default(), number_at(1)?),
+            "forward" => repeat(
+                &mut commands,
+                TickCommand {
+                    forward: 1.0,
+                    ..held(Action::MoveForward)
+                },
+                number_at(1)?,
+            ),
+            "backward" => repeat(
+                &mut commands,
+                TickCommand {
+                    forward: -1.0,
+                    ..held(Action::MoveBackward)
+                },
+                number_at(1)?,
+            ),
+            "left" => repeat(
+                &mut commands,
+                TickCommand {
+                    right: -1.0,
+                    ..held(Action::StrafeLeft)
+                },
+                number_at(1)?,
+            ),
+            "right" => repeat(
+                &mut commands,
+                TickCommand {
+                    right: 1.0,
+                    ..held(Action::StrafeRight)
+                },
+                number_at(1)?,
+            ),
+            "turn" => commands.push(TickCommand {
+                yaw: number_at(1)?,
+                ..Default::default()
+            }),
+            "look" => commands.push(TickCommand {
+                pitch: number_at(1)?,
+                ..Default::default()
+            }),
+            "press" => {
+                let action = action_at(1)?;
+                commands.push(TickCommand {
+                    pressed: action.bit(),
+                    ..held(action)
+                });
+            }
+            "hold" => repeat(&mut commands, held(action_at(1)?), number_at(2)?),
+            _ => return Err(error("unknown command in")),
+        }
+    }
+
+    Ok(commands)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enemies::{load_enemies, CONFIG_PATH as ENEMIES_PATH};
+    use crate::graphics::renderable::mapdata::tests::test_map;
+    use crate::inventory::{load_items, Inventory, ITEMS_PATH};
+    use crate::things::Difficulty;
+    use crate::weapons::{Weapons, CONFIG_PATH as WEAPONS_PATH};
+    use nalgebra_glm as ng;
+
+    fn script(text: &str) -> Source {
+        Source::Script(parse_script(text).unwrap())
+    }
+
+    #[test]
+    fn script_errors() {
+        assert_eq!(
+            parse_script("forward 3\n# comment\n\nturn 90")
+                .unwrap()
+                .len(),
+            4
+        );
+        assert!(parse_script("jump 3").unwrap_err().starts_with("line 1"));
+        assert!(parse_script("wait\n").is_err());
+        assert!(parse_script("wait 1\npress nothing")
+            .unwrap_err()
+            .starts_with("line 2"));
+    }
+
+    #[test]
+    fn idle_ticks() {
+        let mut world = World::load("test2.json", 1, Difficulty::Easy);
+        let spawn = world.state();
+        let state = run(&mut world, Source::Idle(20), false);
+        assert_eq!(state.tick, 20);
+        assert_eq!(state.position, spawn.position);
+    }
+
+    #[test]
+    fn respawn_after_death() {
+        let mut world = World::load("test2.json", 1, Difficulty::Easy);
+        let die = "press debug_damage\nwait 1\n".repeat(5);
+        let state = run(&mut world, script(&format!("{die}wait 30")), false);
+        assert!(state.health <= 0);
+
+        let state = run(&mut world, script("press menu_confirm\nwait 1"), false);
+        assert!(state.health <= 0);
+        let state = run(&mut world, script("press respawn\nwait 1"), false);
+        assert_eq!(state.health, crate::player::MAX_HEALTH);
+    }
+
+    // Back at the checkpoint, facing the way it was reached
+    #[test]
+    fn respawn_at_checkpoint() {
+        let mut map = test_map(
+            r#"[{ "floor": 0.0, "ceiling": 2.0, "corners": [[0, 0], [4, 0], [4, 2], [0, 2]] }]"#,
+        );
+        map.checkpoints.push(map.spawn);
+        let mut world = World::new(
+            map,
+            Weapons::load(WEAPONS_PATH),
+            Inventory::load(WEAPONS_PATH),
+            load_items(ITEMS_PATH),
+            load_enemies(ENEMIES_PATH),
+            1,
+            Difficulty::Easy,
+        );
+
+        let spawn = world.state().position;
+        run(&mut world, script("turn 30"), false);
+        let aim = world.player.aim();
+        let die = "press debug_damage\nwait 1\n".repeat(5);
+        run(
+            &mut world,
+            script(&format!("forward 10\nturn 45\n{die}wait 30")),
+            false,
+        );
+        assert_ne!(world.state().position, spawn);
+
+        let state = run(&mut world, script("press respawn\nwait 1"), false);
+        assert_eq!(state.position, spawn);
+        assert_eq!(world.player.aim(), aim);
+    }
+
+    // The door is in the way until it gets used
+    #[test]
+    fn walk_through_door() {
+        let mut world = World::load("test2.json", 1, Difficulty::Easy);
+        let state = run(&mut world, script("forward 9\nturn 90\nforward 100"), false);
+        assert_eq!(state.sector, 2);
+        let blocked = state.position;
+        let state = run(&mut world, script("forward 10"), false);
+        assert_eq!(state.position, blocked);
+
+        let state = run(&mut world, script("press use\nwait 40\nforward 60"), false);
+        assert_eq!(state.tick, 9 + 1 + 100 + 10 + 1 + 40 + 60);
+        assert_eq!(state.sector, 4);
+    }
+
+    // Spectators fly through the items without taking them
+    #[test]
+    fn spectator_skips_pickups() {
+        let mut world = World::load("test2.json", 1, Difficulty::Easy);
+        let walk = "turn 90\nforward 15\nwait 1";
+        run(
+            &mut world,
+            script(&format!("press toggle_spectator\n{walk}")),
+            false,
+        );
+        assert_eq!(world.pickups[0].hidden, 0.0);
+
+        let mut world = World::load("test2.json", 1, Difficulty::Easy);
+        run(&mut world, script(walk), false);
+        assert!(world.pickups[0].hidden > 0.0);
+    }
+
+    // Shots and explosions find the player like any other actor
+    #[test]
+    fn player_among_actors() {
+        let world = World::load("test2.json", 1, Difficulty::Easy);
+        let player = world.player.id();
+        let sector = world.player.sector(&world.actors);
+        assert!(world.actors.in_sector(sector).any(|(id, _)| id == player));
+
+        let center = world.player.body(&world.actors).center();
+        let origin = center - ng::DVec3::new(2.0, 0.0, 0.0);
+        let direction = ng::DVec3::new(1.0, 0.0, 0.0);
+        let hit = world.actors.raycast(origin, direction, 5.0, None);
+        assert_eq!(hit.map(|(id, _)| id), Some(player));
+        assert!(world
+            .actors
+            .raycast(origin, direction, 5.0, Some(player))
+            .is_none());
+    }
+
+    #[test]
+    fn reachability() {
+        let room = r#"{ "floor": 0.0, "ceiling": 2.0, "corners": [[0, 0], [2, 0], [2, 2], [0, 2]],
+                        "gates": [{ "own": 1, "targetSector": 1, "targetGate": 3 }] },
+                      { "floor": 0.0, "ceiling": 2.0, "corners": [[2, 0], [4, 0], [4, 2], [2, 2]],
+                        "gates": [{ "own": 3, "targetSector": 0, "targetGate": 1 }] }"#;
+        assert_eq!(check_reachability(&test_map(&format!("[{room}]"))), 0);
+
+        let closed =
+            r#"{ "floor": 0.0, "ceiling": 2.0, "corners": [[6, 0], [7, 0], [7, 1], [6, 1]] }"#;
+        assert_eq!(
+            check_reachability(&test_map(&format!("[{room}, {closed}]"))),
+            1
+        );
+    }
+
+    // Items and projectiles get drawn next to the actors
+    #[test]
+    fn sprites_for_things() {
+        let frames = |world: &World| -> Vec<String> {
+            world
+                .sprites()
+                .into_iter()
+                .map(|sprite| sprite.frame)
+                .collect()
+        };
+        let mut world = World::load("test2.json", 1, Difficulty::Easy);
+        assert!(frames(&world).contains(&"SHELA".to_string()));
+        assert!(!frames(&world).contains(&"MISLA".to_string()));
+
+        run(
+            &mut world,
+            script("press weapon_4\nwait 35\nhold fire 2"),
+            false,
+        );
+        assert!(frames(&world).contains(&"MISLA".to_string()));
+    }
+
+    // A room with a shoot trigger on its right wall which dims it
+    fn shooting_range() -> World {
+        let map = test_map(
+            r#"[{ "floor": 0.0, "ceiling": 2.0, "tag": 1,
+                  "corners": [[0, 0], [2, 0], [2, 2], [0, 2]],
+                  "triggers": [{ "edge": 1, "type": "shoot", "action": "light", "light": 0.25,
+                                 "tag": 1 }] }]"#,
+        );
+        World::new(
+            map,
+            Weapons::load(WEAPONS_PATH),
+            Inventory::load(WEAPONS_PATH),
+            load_items(ITEMS_PATH),
+            load_enemies(ENEMIES_PATH),
+            1,
+            Difficulty::Normal,
+        )
+    }
+
+    #[test]
+    fn projectiles_shoot_triggers() {
+        let mut world = shooting_range();
+        run(&mut world, script("press weapon_4\nwait 35"), false);
+        assert_eq!(world.map.sectors[0].light, 1.0);
+        run(&mut world, script("press fire\nwait 10"), false);
+        assert_eq!(world.map.sectors[0].light, 0.25);
+    }
+
+    #[test]
+    #[should_panic(expected = "no teleport destination")]
+    fn teleport_needs_destination() {
+        test_map(
+            r#"[{ "floor": 0.0, "ceiling": 2.0, "tag": 1,
+                  "corners": [[0, 0], [2, 0], [2, 2], [0, 2]],
+                  "triggers": [{ "type": "walk", "action": "teleport", "tag": 1 }] }]"#,
+        );
+    }
+}
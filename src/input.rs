@@ -0,0 +1,710 @@
+use std::collections::{HashMap, HashSet};
+
+pub const CONFIG_PATH: &str = "assets/config/input.json";
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    StrafeLeft,
+    StrafeRight,
+    Jump,
+    FlyDown,
+    Pause,
+    ToggleWireframe,
+    ToggleFps,
+    ToggleSpectator,
+    ToggleVsync,
+    TeleportSpawn,
+    DebugDamage,
+    MenuConfirm,
+    Help,
+    DebugFinish,
+    DebugHeal,
+    DebugArmor,
+    Fire,
+    NextWeapon,
+    PrevWeapon,
+    Weapon1,
+    Weapon2,
+    Weapon3,
+    Weapon4,
+    Weapon5,
+    Use,
+    Respawn, // sent by the game over screen, not bound to anything
+}
+
+impl Action {
+    pub const ALL: [Action; 28] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::Jump,
+        Action::FlyDown,
+        Action::Pause,
+        Action::ToggleWireframe,
+        Action::ToggleFps,
+        Action::ToggleSpectator,
+        Action::ToggleVsync,
+        Action::TeleportSpawn,
+        Action::DebugDamage,
+        Action::MenuConfirm,
+        Action::Help,
+        Action::DebugFinish,
+        Action::DebugHeal,
+        Action::DebugArmor,
+        Action::Fire,
+        Action::NextWeapon,
+        Action::PrevWeapon,
+        Action::Weapon1,
+        Action::Weapon2,
+        Action::Weapon3,
+        Action::Weapon4,
+        Action::Weapon5,
+        Action::Use,
+        Action::Respawn,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveForward => "move_forward",
+            Action::MoveBackward => "move_backward",
+            Action::StrafeLeft => "strafe_left",
+            Action::StrafeRight => "strafe_right",
+            Action::Jump => "jump",
+            Action::FlyDown => "fly_down",
+            Action::Pause => "pause",
+            Action::ToggleWireframe => "toggle_wireframe",
+            Action::ToggleFps => "toggle_fps",
+            Action::ToggleSpectator => "toggle_spectator",
+            Action::ToggleVsync => "toggle_vsync",
+            Action::TeleportSpawn => "teleport_spawn",
+            Action::DebugDamage => "debug_damage",
+            Action::MenuConfirm => "menu_confirm",
+            Action::Help => "help",
+            Action::DebugFinish => "debug_finish",
+            Action::DebugHeal => "debug_heal",
+            Action::DebugArmor => "debug_armor",
+            Action::Fire => "fire",
+            Action::NextWeapon => "next_weapon",
+            Action::PrevWeapon => "prev_weapon",
+            Action::Weapon1 => "weapon_1",
+            Action::Weapon2 => "weapon_2",
+            Action::Weapon3 => "weapon_3",
+            Action::Weapon4 => "weapon_4",
+            Action::Weapon5 => "weapon_5",
+            Action::Use => "use",
+            Action::Respawn => "respawn",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    pub fn bit(&self) -> u32 {
+        1 << *self as u32
+    }
+}
+
+// The actions have to fit into the bits of a TickCommand
+const _: () = assert!(Action::ALL.len() <= u32::BITS as usize);
+
+// Everything the simulation gets to know about the input of one tick
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct TickCommand {
+    pub forward: f64, // -1..1
+    pub right: f64,   // -1..1
+    pub yaw: f64,     // degrees
+    pub pitch: f64,   // degrees
+    pub held: u32,    // bit per action
+    pub pressed: u32, // bit per action pressed since the last tick
+}
+
+impl TickCommand {
+    pub fn held(&self, action: Action) -> bool {
+        self.held & action.bit() != 0
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed & action.bit() != 0
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Binding {
+    Key(glfw::Key, glfw::Modifiers),
+    Mouse(glfw::MouseButton),
+    WheelUp,
+    WheelDown,
+    PadButton(glfw::GamepadButton),
+    PadTrigger(glfw::GamepadAxis),
+}
+
+const KEY_NAMES: [(&str, glfw::Key); 72] = [
+    ("a", glfw::Key::A),
+    ("b", glfw::Key::B),
+    ("c", glfw::Key::C),
+    ("d", glfw::Key::D),
+    ("e", glfw::Key::E),
+    ("f", glfw::Key::F),
+    ("g", glfw::Key::G),
+    ("h", glfw::Key::H),
+    ("i", glfw::Key::I),
+    ("j", glfw::Key::J),
+    ("k", glfw::Key::K),
+    ("l", glfw::Key::L),
+    ("m", glfw::Key::M),
+    ("n", glfw::Key::N),
+    ("o", glfw::Key::O),
+    ("p", glfw::Key::P),
+    ("q", glfw::Key::Q),
+    ("r", glfw::Key::R),
+    ("s", glfw::Key::S),
+    ("t", glfw::Key::T),
+    ("u", glfw::Key::U),
+    ("v", glfw::Key::V),
+    ("w", glfw::Key::W),
+    ("x", glfw::Key::X),
+    ("y", glfw::Key::Y),
+    ("z", glfw::Key::Z),
+    ("0", glfw::Key::Num0),
+    ("1", glfw::Key::Num1),
+    ("2", glfw::Key::Num2),
+    ("3", glfw::Key::Num3),
+    ("4", glfw::Key::Num4),
+    ("5", glfw::Key::Num5),
+    ("6", glfw::Key::Num6),
+    ("7", glfw::Key::Num7),
+    ("8", glfw::Key::Num8),
+    ("9", glfw::Key::Num9),
+    ("f1", glfw::Key::F1),
+    ("f2", glfw::Key::F2),
+    ("f3", glfw::Key::F3),
+    ("f4", glfw::Key::F4),
+    ("f5", glfw::Key::F5),
+    ("f6", glfw::Key::F6),
+    ("f7", glfw::Key::F7),
+    ("f8", glfw::Key::F8),
+    ("f9", glfw::Key::F9),
+    ("f10", glfw::Key::F10),
+    ("f11", glfw::Key::F11),
+    ("f12", glfw::Key::F12),
+    ("space", glfw::Key::Space),
+    ("escape", glfw::Key::Escape),
+    ("enter", glfw::Key::Enter),
+    ("tab", glfw::Key::Tab),
+    ("backspace", glfw::Key::Backspace),
+    ("insert", glfw::Key::Insert),
+    ("delete", glfw::Key::Delete),
+    ("home", glfw::Key::Home),
+    ("end", glfw::Key::End),
+    ("page_up", glfw::Key::PageUp),
+    ("page_down", glfw::Key::PageDown),
+    ("up", glfw::Key::Up),
+    ("down", glfw::Key::Down),
+    ("left", glfw::Key::Left),
+    ("right", glfw::Key::Right),
+    ("left_shift", glfw::Key::LeftShift),
+    ("right_shift", glfw::Key::RightShift),
+    ("left_control", glfw::Key::LeftControl),
+    ("right_control", glfw::Key::RightControl),
+    ("left_alt", glfw::Key::LeftAlt),
+    ("right_alt", glfw::Key::RightAlt),
+    ("comma", glfw::Key::Comma),
+    ("period", glfw::Key::Period),
+    ("minus", glfw::Key::Minus),
+];
+
+const PAD_BUTTON_NAMES: [(&str, glfw::GamepadButton); 15] = [
+    ("pad_a", glfw::GamepadButton::ButtonA),
+    ("pad_b", glfw::GamepadButton::ButtonB),
+    ("pad_x", glfw::GamepadButton::ButtonX),
+    ("pad_y", glfw::GamepadButton::ButtonY),
+    ("pad_left_bumper", glfw::GamepadButton::ButtonLeftBumper),
+    ("pad_right_bumper", glfw::GamepadButton::ButtonRightBumper),
+    ("pad_back", glfw::GamepadButton::ButtonBack),
+    ("pad_start", glfw::GamepadButton::ButtonStart),
+    ("pad_guide", glfw::GamepadButton::ButtonGuide),
+    ("pad_left_thumb", glfw::GamepadButton::ButtonLeftThumb),
+    ("pad_right_thumb", glfw::GamepadButton::ButtonRightThumb),
+    ("pad_up", glfw::GamepadButton::ButtonDpadUp),
+    ("pad_right", glfw::GamepadButton::ButtonDpadRight),
+    ("pad_down", glfw::GamepadButton::ButtonDpadDown),
+    ("pad_left", glfw::GamepadButton::ButtonDpadLeft),
+];
+
+const PAD_TRIGGER_NAMES: [(&str, glfw::GamepadAxis); 2] = [
+    ("pad_left_trigger", glfw::GamepadAxis::AxisLeftTrigger),
+    ("pad_right_trigger", glfw::GamepadAxis::AxisRightTrigger),
+];
+
+// Triggers go from -1 (released) to 1 (fully pressed)
+const TRIGGER_THRESHOLD: f32 = 0.0;
+
+const MODIFIER_NAMES: [(&str, glfw::Modifiers); 3] = [
+    ("ctrl", glfw::Modifiers::Control),
+    ("shift", glfw::Modifiers::Shift),
+    ("alt", glfw::Modifiers::Alt),
+];
+
+impl Binding {
+    // e.g. "w", "ctrl+f", "mouse1", "wheel_up"
+    pub fn parse(text: &str) -> Option<Binding> {
+        let text = text.trim().to_lowercase();
+        match text.as_str() {
+            "wheel_up" => return Some(Binding::WheelUp),
+            "wheel_down" => return Some(Binding::WheelDown),
+            _ => {}
+        }
+
+        if let Some((_, button)) = PAD_BUTTON_NAMES.iter().find(|(name, _)| *name == text) {
+            return Some(Binding::PadButton(*button));
+        }
+        if let Some((_, axis)) = PAD_TRIGGER_NAMES.iter().find(|(name, _)| *name == text) {
+            return Some(Binding::PadTrigger(*axis));
+        }
+
+        if let Some(number) = text.strip_prefix("mouse") {
+            let number: i32 = number.parse().ok()?;
+            return glfw::MouseButton::from_i32(number - 1).map(Binding::Mouse);
+        }
+
+        let mut modifiers = glfw::Modifiers::empty();
+        let mut parts: Vec<&str> = text.split('+').collect();
+        let key = parts.pop()?;
+        for part in parts {
+            let (_, modifier) = MODIFIER_NAMES.iter().find(|(name, _)| *name == part)?;
+            modifiers |= *modifier;
+        }
+
+        let (_, key) = KEY_NAMES.iter().find(|(name, _)| *name == key)?;
+        Some(Binding::Key(*key, modifiers))
+    }
+
+    pub fn to_text(self) -> String {
+        match self {
+            Binding::Key(key, modifiers) => {
+                let mut text = String::new();
+                for (name, modifier) in MODIFIER_NAMES {
+                    if modifiers.contains(modifier) {
+                        text += name;
+                        text += "+";
+                    }
+                }
+                let (name, _) = KEY_NAMES.iter().find(|(_, k)| *k == key).unwrap();
+                text + name
+            }
+            Binding::Mouse(button) => format!("mouse{}", button as i32 + 1),
+            Binding::WheelUp => "wheel_up".to_string(),
+            Binding::WheelDown => "wheel_down".to_string(),
+            Binding::PadButton(button) => {
+                let (name, _) = PAD_BUTTON_NAMES.iter().find(|(_, b)| *b == button).unwrap();
+                name.to_string()
+            }
+            Binding::PadTrigger(axis) => {
+                let (name, _) = PAD_TRIGGER_NAMES.iter().find(|(_, a)| *a == axis).unwrap();
+                name.to_string()
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct GamepadConfig {
+    pub move_dead_zone: f64,
+    pub look_dead_zone: f64,
+    pub move_curve: f64, // exponent applied to the stick magnitude, 1 is linear
+    pub look_curve: f64,
+    pub look_speed: f64, // degrees per second at full deflection
+    pub invert_y: bool,
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        GamepadConfig {
+            move_dead_zone: 0.2,
+            look_dead_zone: 0.15,
+            move_curve: 1.0,
+            look_curve: 2.0,
+            look_speed: 180.0,
+            invert_y: false,
+        }
+    }
+}
+
+// Radial dead zone, the remaining range is rescaled to 0..1 and bent by the curve
+fn apply_stick(x: f32, y: f32, dead_zone: f64, curve: f64) -> (f64, f64) {
+    let (x, y) = (x as f64, y as f64);
+    let magnitude = f64::sqrt(x * x + y * y);
+    if magnitude <= dead_zone {
+        return (0.0, 0.0);
+    }
+
+    let scaled = ((magnitude - dead_zone) / (1.0 - dead_zone))
+        .min(1.0)
+        .powf(curve);
+    (x / magnitude * scaled, y / magnitude * scaled)
+}
+
+pub struct InputMap {
+    bindings: HashMap<Action, Vec<Binding>>,
+    pub mouse_sensitivity: f64, // degrees per pixel
+    pub gamepad: GamepadConfig,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        let defaults = [
+            (Action::MoveForward, "w"),
+            (Action::MoveBackward, "s"),
+            (Action::StrafeLeft, "a"),
+            (Action::StrafeRight, "d"),
+            (Action::Jump, "space"),
+            (Action::FlyDown, "left_shift"),
+            (Action::Pause, "escape"),
+            (Action::ToggleWireframe, "ctrl+f"),
+            (Action::ToggleFps, "ctrl+p"),
+            (Action::ToggleSpectator, "ctrl+s"),
+            (Action::ToggleVsync, "ctrl+v"),
+            (Action::TeleportSpawn, "ctrl+t"),
+            (Action::DebugDamage, "ctrl+h"),
+            (Action::MenuConfirm, "enter"),
+            (Action::Help, "f1"),
+            (Action::DebugFinish, "ctrl+n"),
+            (Action::DebugHeal, "ctrl+j"),
+            (Action::DebugArmor, "ctrl+k"),
+            (Action::Fire, "mouse1"),
+            (Action::NextWeapon, "wheel_down"),
+            (Action::PrevWeapon, "wheel_up"),
+            (Action::Weapon1, "1"),
+            (Action::Weapon2, "2"),
+            (Action::Weapon3, "3"),
+            (Action::Weapon4, "4"),
+            (Action::Weapon5, "5"),
+            (Action::Use, "e"),
+            (Action::MenuConfirm, "pad_a"),
+            (Action::Jump, "pad_a"),
+            (Action::FlyDown, "pad_b"),
+            (Action::Pause, "pad_start"),
+            (Action::ToggleSpectator, "pad_back"),
+            (Action::Fire, "pad_right_trigger"),
+            (Action::NextWeapon, "pad_right_bumper"),
+            (Action::PrevWeapon, "pad_left_bumper"),
+            (Action::Use, "pad_x"),
+        ];
+
+        let mut map = InputMap {
+            bindings: HashMap::new(),
+            mouse_sensitivity: 0.12,
+            gamepad: GamepadConfig::default(),
+        };
+        for (action, binding) in defaults {
+            map.bind(action, Binding::parse(binding).unwrap());
+        }
+        map
+    }
+}
+
+impl InputMap {
+    // Falls back to (and writes) the default bindings if the file does not exist
+    pub fn load(path: &str) -> Self {
+        let Ok(input) = std::fs::read_to_string(path) else {
+            let map = InputMap::default();
+            map.save(path);
+            return map;
+        };
+        let config = json::parse(&input).expect("Could not parse input config");
+
+        let mut map = InputMap::default();
+        for (name, bindings) in config.entries() {
+            if name == "gamepad" {
+                map.load_gamepad(bindings);
+                continue;
+            }
+            if name == "mouseSensitivity" {
+                map.mouse_sensitivity = bindings.as_f64().unwrap_or(map.mouse_sensitivity);
+                continue;
+            }
+
+            let Some(action) = Action::from_name(name) else {
+                println!("Unknown action '{name}' in '{path}'");
+                continue;
+            };
+
+            map.bindings.remove(&action);
+            for binding in bindings.members() {
+                match binding.as_str().and_then(Binding::parse) {
+                    Some(binding) => map.bind(action, binding),
+                    None => println!("Invalid binding {binding} for '{name}' in '{path}'"),
+                }
+            }
+        }
+        map
+    }
+
+    pub fn save(&self, path: &str) {
+        let mut config = json::JsonValue::new_object();
+        for action in Action::ALL {
+            let mut list = json::JsonValue::new_array();
+            for binding in self.bindings(action) {
+                list.push(binding.to_text()).unwrap();
+            }
+            config[action.name()] = list;
+        }
+
+        config["mouseSensitivity"] = self.mouse_sensitivity.into();
+
+        let pad = &self.gamepad;
+        config["gamepad"] = json::object! {
+            "moveDeadZone": pad.move_dead_zone,
+            "lookDeadZone": pad.look_dead_zone,
+            "moveCurve": pad.move_curve,
+            "lookCurve": pad.look_curve,
+            "lookSpeed": pad.look_speed,
+            "invertY": pad.invert_y,
+        };
+
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        if std::fs::write(path, config.pretty(4)).is_err() {
+            println!("Could not save input config '{path}'");
+        }
+    }
+
+    fn load_gamepad(&mut self, json: &json::JsonValue) {
+        let pad = &mut self.gamepad;
+        pad.move_dead_zone = json["moveDeadZone"].as_f64().unwrap_or(pad.move_dead_zone);
+        pad.look_dead_zone = json["lookDeadZone"].as_f64().unwrap_or(pad.look_dead_zone);
+        pad.move_curve = json["moveCurve"].as_f64().unwrap_or(pad.move_curve);
+        pad.look_curve = json["lookCurve"].as_f64().unwrap_or(pad.look_curve);
+        pad.look_speed = json["lookSpeed"].as_f64().unwrap_or(pad.look_speed);
+        pad.invert_y = json["invertY"].as_bool().unwrap_or(pad.invert_y);
+    }
+
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        let list = self.bindings.entry(action).or_default();
+        if !list.contains(&binding) {
+            list.push(binding);
+        }
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        match self.bindings.get(&action) {
+            Some(list) => list,
+            None => &[],
+        }
+    }
+
+    // Bindings with modifiers win over plain ones, so Ctrl+S does not also walk backwards
+    fn actions_for(&self, matches: impl Fn(&Binding) -> bool) -> Vec<Action> {
+        let mut plain = Vec::new();
+        let mut modified = Vec::new();
+        for (action, bindings) in &self.bindings {
+            for binding in bindings {
+                if matches(binding) {
+                    match binding {
+                        Binding::Key(_, modifiers) if !modifiers.is_empty() => {
+                            modified.push(*action)
+                        }
+                        _ => plain.push(*action),
+                    }
+                }
+            }
+        }
+
+        if modified.is_empty() {
+            plain
+        } else {
+            modified
+        }
+    }
+}
+
+// Translates window events and gamepad state into held and freshly pressed actions
+#[derive(Default)]
+pub struct InputState {
+    held: HashSet<Action>,
+    pad_held: HashSet<Action>,
+    pressed: u32, // since the last command
+
+    pad_move: (f64, f64), // forward, right
+    pad_look: (f64, f64), // yaw, pitch in degrees per second
+
+    pub capture_mouse: bool,
+    cursor_last: Option<(f64, f64)>,
+    mouse_look: (f64, f64), // yaw, pitch in degrees since the last command
+}
+
+impl InputState {
+    // Returns the actions which got pressed by the event
+    pub fn handle_event(&mut self, map: &InputMap, event: &glfw::WindowEvent) -> Vec<Action> {
+        let actions = self.event_actions(map, event);
+        for action in &actions {
+            self.pressed |= action.bit();
+        }
+        actions
+    }
+
+    fn event_actions(&mut self, map: &InputMap, event: &glfw::WindowEvent) -> Vec<Action> {
+        match event {
+            glfw::WindowEvent::CursorPos(x, y) => {
+                if let Some(last) = self.cursor_last {
+                    if self.capture_mouse {
+                        self.mouse_look.0 += (x - last.0) * map.mouse_sensitivity;
+                        self.mouse_look.1 += (last.1 - y) * map.mouse_sensitivity;
+                    }
+                }
+                self.cursor_last = Some((*x, *y));
+                Vec::new()
+            }
+            glfw::WindowEvent::Key(key, _, glfw::Action::Press, modifiers) => {
+                let actions = map.actions_for(|binding| match binding {
+                    Binding::Key(k, m) => k == key && modifiers.contains(*m),
+                    _ => false,
+                });
+                self.held.extend(actions.iter());
+                actions
+            }
+            glfw::WindowEvent::Key(key, _, glfw::Action::Release, _) => {
+                for action in Action::ALL {
+                    if map
+                        .bindings(action)
+                        .iter()
+                        .any(|binding| matches!(binding, Binding::Key(k, _) if k == key))
+                    {
+                        self.held.remove(&action);
+                    }
+                }
+                Vec::new()
+            }
+            glfw::WindowEvent::MouseButton(button, glfw::Action::Press, _) => {
+                let actions = map.actions_for(|binding| *binding == Binding::Mouse(*button));
+                self.held.extend(actions.iter());
+                actions
+            }
+            glfw::WindowEvent::MouseButton(button, glfw::Action::Release, _) => {
+                for action in map.actions_for(|binding| *binding == Binding::Mouse(*button)) {
+                    self.held.remove(&action);
+                }
+                Vec::new()
+            }
+            glfw::WindowEvent::Scroll(_, y) if *y > 0.0 => {
+                map.actions_for(|binding| *binding == Binding::WheelUp)
+            }
+            glfw::WindowEvent::Scroll(_, y) if *y < 0.0 => {
+                map.actions_for(|binding| *binding == Binding::WheelDown)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    // Reads the first connected gamepad, returns the actions which got pressed since the last poll
+    pub fn poll_gamepad(&mut self, map: &InputMap, glfw: &glfw::Glfw) -> Vec<Action> {
+        let state = (0..16)
+            .filter_map(glfw::JoystickId::from_i32)
+            .map(|id| glfw.get_joystick(id))
+            .find(|joystick| joystick.is_gamepad())
+            .and_then(|joystick| joystick.get_gamepad_state());
+
+        let Some(state) = state else {
+            self.pad_held.clear();
+            self.pad_move = (0.0, 0.0);
+            self.pad_look = (0.0, 0.0);
+            return Vec::new();
+        };
+
+        let config = &map.gamepad;
+        let (x, y) = apply_stick(
+            state.get_axis(glfw::GamepadAxis::AxisLeftX),
+            state.get_axis(glfw::GamepadAxis::AxisLeftY),
+            config.move_dead_zone,
+            config.move_curve,
+        );
+        self.pad_move = (-y, x); // stick up is negative
+
+        let (x, y) = apply_stick(
+            state.get_axis(glfw::GamepadAxis::AxisRightX),
+            state.get_axis(glfw::GamepadAxis::AxisRightY),
+            config.look_dead_zone,
+            config.look_curve,
+        );
+        self.pad_look = (
+            x * config.look_speed,
+            y * config.look_speed * if config.invert_y { 1.0 } else { -1.0 },
+        );
+
+        let held: HashSet<Action> = map
+            .actions_for(|binding| match binding {
+                Binding::PadButton(button) => {
+                    state.get_button_state(*button) == glfw::Action::Press
+                }
+                Binding::PadTrigger(axis) => state.get_axis(*axis) > TRIGGER_THRESHOLD,
+                _ => false,
+            })
+            .into_iter()
+            .collect();
+
+        let pressed: Vec<Action> = held.difference(&self.pad_held).copied().collect();
+        for action in &pressed {
+            self.pressed |= action.bit();
+        }
+        self.pad_held = held;
+        pressed
+    }
+
+    pub fn held(&self, action: Action) -> bool {
+        self.held.contains(&action) || self.pad_held.contains(&action)
+    }
+
+    // forward and right in -1..1, keys and the left stick combined
+    fn movement(&self) -> (f64, f64) {
+        let axis = |positive: Action, negative: Action| {
+            self.held(positive) as i32 as f64 - self.held(negative) as i32 as f64
+        };
+        let forward = axis(Action::MoveForward, Action::MoveBackward);
+        let right = axis(Action::StrafeRight, Action::StrafeLeft);
+
+        (
+            (forward + self.pad_move.0).clamp(-1.0, 1.0),
+            (right + self.pad_move.1).clamp(-1.0, 1.0),
+        )
+    }
+
+    // Mouse look which is not part of a command yet, so rendering can show it right away
+    pub fn pending_look(&self) -> (f64, f64) {
+        self.mouse_look
+    }
+
+    // Collects the input for the next tick
+    pub fn command(&mut self, delta_time: f64) -> TickCommand {
+        let (forward, right) = self.movement();
+        let mut held = 0;
+        for action in Action::ALL {
+            if self.held(action) {
+                held |= action.bit();
+            }
+        }
+
+        let command = TickCommand {
+            forward,
+            right,
+            yaw: self.mouse_look.0 + self.pad_look.0 * delta_time,
+            pitch: self.mouse_look.1 + self.pad_look.1 * delta_time,
+            held,
+            pressed: self.pressed,
+        };
+
+        self.pressed = 0;
+        self.mouse_look = (0.0, 0.0);
+        command
+    }
+
+    // e.g. when the window loses focus
+    pub fn release_all(&mut self) {
+        self.held.clear();
+        self.cursor_last = None;
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::{HashMap, HashSet};
+
+pub const ITEMS_PATH: &str = "assets/config/items.json";
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Powerup {
+    Invulnerability,
+    QuadDamage,
+}
+
+impl Powerup {
+    pub const ALL: [Powerup; 2] = [Powerup::Invulnerability, Powerup::QuadDamage];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Powerup::Invulnerability => "invulnerability",
+            Powerup::QuadDamage => "quad_damage",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Powerup> {
+        Powerup::ALL
+            .into_iter()
+            .find(|powerup| powerup.name() == name)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ItemKind {
+    Health { amount: i32, overheal: bool },
+    Armor { amount: i32, absorption: f64 },
+    Ammo { ammo: String, amount: i32 },
+    Key(String),
+    Powerup { powerup: Powerup, duration: f64 },
+}
+
+#[derive(Clone, Debug)]
+pub struct ItemDef {
+    pub kind: ItemKind,
+    pub message: String,
+    pub sprite: Option<String>, // None is not drawn
+}
+
+impl ItemDef {
+    fn parse(json: &json::JsonValue) -> Option<Self> {
+        let kind = match json["type"].as_str()? {
+            "health" => ItemKind::Health {
+                amount: json["amount"].as_i32()?,
+                overheal: json["overheal"].as_bool().unwrap_or(false),
+            },
+            "armor" => ItemKind::Armor {
+                amount: json["amount"].as_i32()?,
+                absorption: json["absorption"].as_f64()?,
+            },
+            "ammo" => ItemKind::Ammo {
+                ammo: json["ammo"].as_str()?.to_string(),
+                amount: json["amount"].as_i32()?,
+            },
+            "key" => ItemKind::Key(json["key"].as_str()?.to_string()),
+            "powerup" => ItemKind::Powerup {
+                powerup: Powerup::from_name(json["powerup"].as_str()?)?,
+                duration: json["duration"].as_f64()?,
+            },
+            _ => return None,
+        };
+
+        Some(ItemDef {
+            kind,
+            message: json["message"].as_str().unwrap_or_default().to_string(),
+            sprite: json["sprite"].as_str().map(str::to_string),
+        })
+    }
+}
+
+// All item definitions by name
+pub fn load_items(path: &str) -> HashMap<String, ItemDef> {
+    let input = std::fs::read_to_string(path).expect("Could not find item list");
+    let config = json::parse(&input).expect("Could not parse item list");
+
+    let mut items = HashMap::new();
+    for (name, item) in config.entries() {
+        match ItemDef::parse(item) {
+            Some(def) => {
+                items.insert(name.to_string(), def);
+            }
+            None => println!("Invalid item '{name}' in '{path}'"),
+        }
+    }
+    items
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Inventory {
+    ammo: HashMap<String, (i32, i32)>, // amount, max
+    keys: HashSet<String>,
+    powerups: HashMap<Powerup, f64>, // seconds left
+}
+
+impl Inventory {
+    // Ammo types with their start and max amounts from the "ammo" block of the weapon list
+    pub fn load(path: &str) -> Self {
+        let input = std::fs::read_to_string(path).expect("Could not find weapon list");
+        let config = json::parse(&input).expect("Could not parse weapon list");
+
+        let mut inventory = Inventory::default();
+        for (kind, ammo) in config["ammo"].entries() {
+            inventory.ammo.insert(
+                kind.to_string(),
+                (
+                    ammo["start"].as_i32().unwrap_or(0),
+                    ammo["max"].as_i32().unwrap_or(0),
+                ),
+            );
+        }
+        inventory
+    }
+
+    // Returns false if there is not enough
+    pub fn take_ammo(&mut self, kind: &str, amount: i32) -> bool {
+        match self.ammo.get_mut(kind) {
+            Some(ammo) if ammo.0 >= amount => {
+                ammo.0 -= amount;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Returns false if the ammo is unknown or already full
+    pub fn add_ammo(&mut self, kind: &str, amount: i32) -> bool {
+        match self.ammo.get_mut(kind) {
+            Some(ammo) if ammo.0 < ammo.1 => {
+                ammo.0 = (ammo.0 + amount).min(ammo.1);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Returns false if the key was already there
+    pub fn add_key(&mut self, key: &str) -> bool {
+        self.keys.insert(key.to_string())
+    }
+
+    pub fn has_key(&self, key: &str) -> bool {
+        self.keys.contains(key)
+    }
+
+    // Picking up the same powerup again restarts it
+    pub fn add_powerup(&mut self, powerup: Powerup, duration: f64) {
+        self.powerups.insert(powerup, duration);
+    }
+
+    pub fn has_powerup(&self, powerup: Powerup) -> bool {
+        self.powerups.contains_key(&powerup)
+    }
+
+    pub fn update(&mut self, delta_time: f64) {
+        self.powerups.retain(|_, time| {
+            *time -= delta_time;
+            *time > 0.0
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bullets(amount: i32, max: i32) -> Inventory {
+        let mut inventory = Inventory::default();
+        inventory.ammo.insert("bullets".to_string(), (amount, max));
+        inventory
+    }
+
+    #[test]
+    fn ammo_capped() {
+        let mut inventory = bullets(10, 20);
+        assert!(inventory.add_ammo("bullets", 15));
+        assert_eq!(inventory.ammo["bullets"], (20, 20));
+
+        assert!(inventory.take_ammo("bullets", 20));
+        assert!(!inventory.take_ammo("bullets", 1));
+        assert_eq!(inventory.ammo["bullets"], (0, 20));
+    }
+
+    // Full ammo leaves the pickup lying around
+    #[test]
+    fn pickup_at_cap() {
+        let mut inventory = bullets(20, 20);
+        assert!(!inventory.add_ammo("bullets", 5));
+        assert!(!inventory.add_ammo("shells", 5));
+        assert_eq!(inventory.ammo["bullets"], (20, 20));
+
+        assert!(inventory.take_ammo("bullets", 1));
+        assert!(inventory.add_ammo("bullets", 5));
+        assert_eq!(inventory.ammo["bullets"], (20, 20));
+    }
+
+    #[test]
+    fn keys() {
+        let mut inventory = Inventory::default();
+        assert!(!inventory.has_key("red"));
+        assert!(inventory.add_key("red"));
+        assert!(!inventory.add_key("red"));
+        assert!(inventory.has_key("red"));
+        assert!(!inventory.has_key("blue"));
+    }
+
+    #[test]
+    fn powerups_run_out() {
+        let mut inventory = Inventory::default();
+        inventory.add_powerup(Powerup::QuadDamage, 1.0);
+        inventory.update(0.5);
+        assert!(inventory.has_powerup(Powerup::QuadDamage));
+        inventory.update(0.5);
+        assert!(!inventory.has_powerup(Powerup::QuadDamage));
+    }
+
+    // The ammo items refer to ammo of the weapon list
+    #[test]
+    fn items_match_ammo() {
+        let inventory = Inventory::load(crate::weapons::CONFIG_PATH);
+        for item in load_items(ITEMS_PATH).values() {
+            if let ItemKind::Ammo { ammo, .. } = &item.kind {
+                assert!(inventory.ammo.contains_key(ammo), "unknown ammo {ammo}");
+            }
+        }
+    }
+}
@@ -0,0 +1,331 @@
+use crate::damage::{Damage, DamageType};
+use crate::graphics::renderable::mapdata::Map;
+
+const SPEED: f32 = 1.0; // units per second
+const WAIT: f64 = 3.0; // seconds at the other height
+const CRUSH_DAMAGE: i32 = 1;
+const CRUSH_INTERVAL: f64 = 0.5; // seconds between damage to something being crushed
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LiftKind {
+    Lift,    // goes to the other height and comes back after waiting, turns around when blocked
+    Raise,   // goes to the other height and stays, for rising stairs
+    Crusher, // goes back and forth and hurts what is in the way
+}
+
+impl LiftKind {
+    pub fn from_name(name: &str) -> Option<LiftKind> {
+        match name {
+            "lift" => Some(LiftKind::Lift),
+            "raise" => Some(LiftKind::Raise),
+            "crusher" => Some(LiftKind::Crusher),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LiftTrigger {
+    Walk, // the player steps onto it
+    Use,  // the player uses it or the gate to it
+}
+
+// A sector whose floor moves between where it rests and another height
+#[derive(Clone, Debug)]
+pub struct LiftDef {
+    pub kind: LiftKind,
+    pub trigger: LiftTrigger,
+    pub rest: f32, // floor at the start
+    pub other: f32,
+    pub speed: f32, // units per second
+    pub wait: f64,
+    pub damage: Damage, // per interval while crushing
+}
+
+impl LiftDef {
+    pub fn parse(json: &json::JsonValue, rest: f32) -> Option<Self> {
+        let kind = match json["type"].as_str() {
+            Some(name) => LiftKind::from_name(name)?,
+            None => LiftKind::Lift,
+        };
+        let trigger = match json["trigger"].as_str() {
+            None | Some("walk") => LiftTrigger::Walk,
+            Some("use") => LiftTrigger::Use,
+            Some(_) => return None,
+        };
+        let speed = json["speed"].as_f32().unwrap_or(SPEED);
+        if speed <= 0.0 {
+            return None;
+        }
+
+        Some(LiftDef {
+            kind,
+            trigger,
+            rest,
+            other: json["to"].as_f32()?,
+            speed,
+            wait: json["wait"].as_f64().unwrap_or(WAIT),
+            damage: Damage::new(
+                json["damage"].as_i32().unwrap_or(CRUSH_DAMAGE),
+                DamageType::Environmental,
+            ),
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LiftState {
+    Resting,
+    Leaving, // moving to the other height
+    Waiting,
+    Returning,
+    Finished, // stays at the other height
+}
+
+impl LiftState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LiftState::Resting => "resting",
+            LiftState::Leaving => "leaving",
+            LiftState::Waiting => "waiting",
+            LiftState::Returning => "returning",
+            LiftState::Finished => "finished",
+        }
+    }
+}
+
+// What happened during an update
+#[derive(Default)]
+pub struct LiftChanges {
+    pub states: Vec<(u32, LiftState)>, // lifts which got a new state
+    pub crushed: Vec<(usize, Damage)>, // occupants
+}
+
+struct Lift {
+    sector: u32,
+    state: LiftState,
+    time: f64, // seconds left waiting, or until crushing again
+}
+
+// All lifts of a map, they start resting
+pub struct Lifts {
+    lifts: Vec<Lift>,
+}
+
+impl Lifts {
+    pub fn new(map: &Map) -> Self {
+        let lifts = map
+            .sectors
+            .iter()
+            .enumerate()
+            .filter(|(_, sector)| sector.lift.is_some())
+            .map(|(index, _)| Lift {
+                sector: index as u32,
+                state: LiftState::Resting,
+                time: 0.0,
+            })
+            .collect();
+        Lifts { lifts }
+    }
+
+    // Starts a resting lift with the given trigger, returns true if it did
+    pub fn activate(&mut self, map: &Map, sector: u32, trigger: LiftTrigger) -> bool {
+        match &map.sectors[sector as usize].lift {
+            Some(def) if def.trigger == trigger => self.start(sector),
+            _ => false,
+        }
+    }
+
+    // Starts a resting lift whatever its trigger is, for switches elsewhere
+    pub fn start(&mut self, sector: u32) -> bool {
+        let Some(lift) = self.lifts.iter_mut().find(|lift| lift.sector == sector) else {
+            return false;
+        };
+        if lift.state != LiftState::Resting {
+            return false;
+        }
+
+        lift.state = LiftState::Leaving;
+        true
+    }
+
+    // Occupants are the sectors and heights of everything standing on floors, they get carried along.
+    // A rising floor stops when something does not fit below the ceiling anymore, lifts turn around
+    // like doors do and crushers hurt it
+    pub fn update(
+        &mut self,
+        map: &mut Map,
+        occupants: &[(u32, f32)],
+        delta_time: f64,
+    ) -> LiftChanges {
+        let mut changes = LiftChanges::default();
+        for lift in &mut self.lifts {
+            let sector = &map.sectors[lift.sector as usize];
+            let Some(def) = sector.lift.clone() else {
+                continue;
+            };
+            let (floor, ceiling) = (sector.floor, sector.ceiling);
+
+            let before = lift.state;
+            let goal = match lift.state {
+                LiftState::Leaving => def.other,
+                LiftState::Returning => def.rest,
+                LiftState::Waiting => {
+                    lift.time -= delta_time;
+                    if lift.time <= 0.0 {
+                        lift.state = LiftState::Returning;
+                    }
+                    floor
+                }
+                LiftState::Resting | LiftState::Finished => floor,
+            };
+
+            if goal != floor {
+                let step = def.speed * delta_time as f32;
+                let moved = if goal > floor {
+                    (floor + step).min(goal)
+                } else {
+                    (floor - step).max(goal)
+                };
+
+                let blocking: Vec<usize> = occupants
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (inside, height))| {
+                        *inside == lift.sector && moved > floor && moved + height > ceiling
+                    })
+                    .map(|(i, _)| i)
+                    .collect();
+
+                if blocking.is_empty() {
+                    map.set_heights(lift.sector, moved, ceiling);
+                } else if def.kind == LiftKind::Lift {
+                    lift.state = match lift.state {
+                        LiftState::Leaving => LiftState::Returning,
+                        _ => LiftState::Leaving,
+                    };
+                } else if def.kind == LiftKind::Crusher {
+                    lift.time -= delta_time;
+                    if lift.time <= 0.0 {
+                        lift.time = CRUSH_INTERVAL;
+                        changes
+                            .crushed
+                            .extend(blocking.into_iter().map(|i| (i, def.damage)));
+                    }
+                }
+
+                if map.sectors[lift.sector as usize].floor == goal {
+                    lift.time = def.wait;
+                    lift.state = match (lift.state, def.kind) {
+                        (LiftState::Leaving, LiftKind::Raise) => LiftState::Finished,
+                        (LiftState::Leaving, _) => LiftState::Waiting,
+                        // Crushers keep going
+                        (_, LiftKind::Crusher) => LiftState::Leaving,
+                        _ => LiftState::Resting,
+                    };
+                }
+            }
+
+            if lift.state != before {
+                changes.states.push((lift.sector, lift.state));
+            }
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::renderable::mapdata::tests::test_map;
+
+    const TICK: f64 = 0.125; // exact in binary so the waits end on a tick
+
+    // A room with a lift which lowers from 1 to 0 in a second and waits for 1 second
+    fn lift_room(kind: &str) -> (Map, Lifts) {
+        let map = test_map(&format!(
+            r#"[{{ "floor": 1.0, "ceiling": 2.0, "corners": [[0, 0], [2, 0], [2, 2], [0, 2]],
+                   "lift": {{ "type": "{kind}", "to": 0.0, "speed": 1.0, "wait": 1.0 }} }}]"#
+        ));
+        let lifts = Lifts::new(&map);
+        (map, lifts)
+    }
+
+    // Ticks until the lift gets a new state, returns it and the ticks it took
+    fn run(map: &mut Map, lifts: &mut Lifts, occupants: &[(u32, f32)]) -> (LiftState, u32) {
+        for tick in 1..=100 {
+            if let Some(&(_, state)) = lifts.update(map, occupants, TICK).states.first() {
+                return (state, tick);
+            }
+        }
+        panic!("Lift stuck");
+    }
+
+    fn heights(map: &Map) -> (f32, f32) {
+        (map.sectors[0].floor, map.sectors[0].ceiling)
+    }
+
+    #[test]
+    fn full_cycle() {
+        let (mut map, mut lifts) = lift_room("lift");
+        assert!(!lifts.activate(&map, 0, LiftTrigger::Use));
+        assert!(lifts.activate(&map, 0, LiftTrigger::Walk));
+        assert!(!lifts.start(0));
+
+        lifts.update(&mut map, &[], 0.5);
+        assert_eq!(heights(&map), (0.5, 2.0));
+        assert_eq!(run(&mut map, &mut lifts, &[]).0, LiftState::Waiting);
+        assert_eq!(heights(&map), (0.0, 2.0));
+
+        assert_eq!(run(&mut map, &mut lifts, &[]), (LiftState::Returning, 8));
+        assert_eq!(heights(&map), (0.0, 2.0));
+
+        lifts.update(&mut map, &[], 0.5);
+        assert_eq!(heights(&map), (0.5, 2.0));
+        assert_eq!(run(&mut map, &mut lifts, &[]).0, LiftState::Resting);
+        assert_eq!(heights(&map), (1.0, 2.0));
+        assert!(lifts.start(0));
+    }
+
+    // Something too tall for the way up sends the lift back down
+    #[test]
+    fn reverses_when_blocked() {
+        let (mut map, mut lifts) = lift_room("lift");
+        lifts.start(0);
+        run(&mut map, &mut lifts, &[]);
+        run(&mut map, &mut lifts, &[]);
+
+        let tall = [(0, 1.55)];
+        assert_eq!(run(&mut map, &mut lifts, &tall), (LiftState::Leaving, 4));
+        let (floor, ceiling) = heights(&map);
+        assert!(floor + tall[0].1 <= ceiling);
+        assert!(floor > 0.35);
+
+        assert_eq!(run(&mut map, &mut lifts, &tall).0, LiftState::Waiting);
+        assert_eq!(heights(&map), (0.0, 2.0));
+        assert_eq!(run(&mut map, &mut lifts, &[]).0, LiftState::Returning);
+    }
+
+    #[test]
+    fn raise_and_crush() {
+        // Rising stairs stay at the other height
+        let (mut map, mut lifts) = lift_room("raise");
+        lifts.start(0);
+        assert_eq!(run(&mut map, &mut lifts, &[]).0, LiftState::Finished);
+        assert_eq!(heights(&map), (0.0, 2.0));
+        assert!(!lifts.start(0));
+
+        // Crushers keep going back and forth and hurt what does not fit
+        let (mut map, mut lifts) = lift_room("crusher");
+        lifts.start(0);
+        run(&mut map, &mut lifts, &[]);
+        run(&mut map, &mut lifts, &[]);
+        let tall = [(0, 1.8)];
+        let mut crushed = 0;
+        for _ in 0..20 {
+            crushed += lifts.update(&mut map, &tall, TICK).crushed.len();
+        }
+        assert_eq!(crushed, 5);
+        assert_eq!(heights(&map), (0.125, 2.0));
+    }
+}
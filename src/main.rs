@@ -3,18 +3,181 @@ extern crate glfw;
 extern crate image;
 extern crate nalgebra_glm;
 
+mod actors;
+mod damage;
+mod demo;
+mod doors;
+mod enemies;
+mod geometry;
 pub mod graphics;
+mod headless;
+mod input;
+mod inventory;
+mod lifts;
+mod navigation;
+mod noise;
 mod player;
+mod random;
+mod states;
+mod things;
+mod timing;
+mod triggers;
 mod utils;
+mod weapons;
+mod world;
 
 use glfw::Context;
+use things::Difficulty;
 
 const TITLE: &str = "mooD - C++ mag niemand";
 
 const INIT_WIDTH: u32 = 1280;
 const INIT_HEIGHT: u32 = 720;
 
+const DEFAULT_MAP: &str = "test2.json";
+
+const VSYNC: bool = true;
+const MAX_FPS: Option<f64> = Some(300.0); // only used without vsync
+
+struct Options {
+    map: String,
+    record: Option<String>,
+    play: Option<String>,
+    seed: Option<u64>,
+    difficulty: Difficulty,
+
+    headless: bool,
+    script: Option<String>,
+    ticks: u64,
+    trace: bool,
+    reachability: bool,
+}
+
+// mood [--map <file>] [--record <demo>] [--play <demo>] [--seed <n>]
+//      [--difficulty <easy|normal|hard>]
+//      [--headless [--script <file>] [--ticks <n>] [--trace] [--reachability]]
+fn parse_args() -> Options {
+    let mut options = Options {
+        map: DEFAULT_MAP.to_string(),
+        record: None,
+        play: None,
+        seed: None,
+        difficulty: Difficulty::Normal,
+
+        headless: false,
+        script: None,
+        ticks: 0,
+        trace: false,
+        reachability: false,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--map" => options.map = args.next().expect("--map needs a file"),
+            "--record" => {
+                options.record = Some(demo_path(args.next().expect("--record needs a file")))
+            }
+            "--play" => options.play = Some(demo_path(args.next().expect("--play needs a file"))),
+            "--seed" => {
+                options.seed = Some(
+                    args.next()
+                        .and_then(|seed| seed.parse().ok())
+                        .expect("--seed needs a number"),
+                )
+            }
+            "--difficulty" => {
+                options.difficulty = args
+                    .next()
+                    .and_then(|name| Difficulty::from_name(&name))
+                    .expect("--difficulty needs easy, normal or hard")
+            }
+            "--headless" => options.headless = true,
+            "--script" => options.script = Some(args.next().expect("--script needs a file")),
+            "--ticks" => {
+                options.ticks = args
+                    .next()
+                    .and_then(|ticks| ticks.parse().ok())
+                    .expect("--ticks needs a number")
+            }
+            "--trace" => options.trace = true,
+            "--reachability" => options.reachability = true,
+            _ => println!("Unknown argument '{arg}'"),
+        }
+    }
+
+    options
+}
+
+fn demo_path(path: String) -> String {
+    if path.ends_with(demo::EXTENSION) {
+        path
+    } else {
+        format!("{path}.{}", demo::EXTENSION)
+    }
+}
+
+fn run_headless(options: &Options, seed: u64, playback: Option<demo::Playback>) {
+    if options.reachability {
+        let mut map = graphics::renderable::mapdata::Map::new();
+        map.load(&options.map);
+        // Fails map checks in scripts
+        if headless::check_reachability(&map) > 0 {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let source = match (playback, &options.script) {
+        (Some(playback), _) => headless::Source::Demo(playback),
+        (None, Some(path)) => {
+            let script = std::fs::read_to_string(path).expect("Could not read script");
+            match headless::parse_script(&script) {
+                Ok(commands) => headless::Source::Script(commands),
+                Err(err) => {
+                    println!("Invalid script '{path}': {err}");
+                    return;
+                }
+            }
+        }
+        (None, None) => headless::Source::Idle(options.ticks),
+    };
+
+    let mut world = world::World::load(&options.map, seed, options.difficulty);
+    let state = headless::run(&mut world, source, options.trace);
+    println!("{state}");
+}
+
 fn main() {
+    let mut options = parse_args();
+
+    let playback = options
+        .play
+        .as_ref()
+        .map(|path| demo::Playback::new(demo::Demo::load(path).expect("Could not load demo")));
+    if let Some(playback) = &playback {
+        options.map = playback.demo().map.clone();
+        options.difficulty = playback.demo().difficulty;
+    }
+
+    let seed = match (&playback, options.seed) {
+        (Some(playback), _) => playback.demo().seed,
+        (None, Some(seed)) => seed,
+        (None, None) => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|time| time.as_secs())
+            .unwrap_or(0),
+    };
+    let recording = options
+        .record
+        .as_ref()
+        .map(|_| demo::Demo::new(&options.map, seed, options.difficulty));
+
+    if options.headless {
+        run_headless(&options, seed, playback);
+        return;
+    }
+
     let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).expect("Failed on initilizing glfw.");
 
     let (mut window, events) = glfw
@@ -24,16 +187,22 @@ fn main() {
     window.set_key_polling(true);
     window.set_cursor_pos_polling(true);
     window.set_scroll_polling(true);
+    window.set_mouse_button_polling(true);
+    window.set_focus_polling(true);
 
-    window.set_cursor_mode(glfw::CursorMode::Disabled);
     window.set_resizable(false);
 
     window.make_current();
 
+    let mut vsync = VSYNC;
+    glfw.set_swap_interval(if vsync {
+        glfw::SwapInterval::Sync(1)
+    } else {
+        glfw::SwapInterval::None
+    });
+
     let wnd_last_size = window.get_size();
 
-    let mut fill_mode = true;
-    let mut paused = false;
     #[cfg(debug_assertions)]
     let mut show_fps = true;
     #[cfg(not(debug_assertions))]
@@ -45,115 +214,151 @@ fn main() {
     let mut graphics = graphics::Graphics::init();
     graphics.resize(wnd_last_size.0 as u32, wnd_last_size.1 as u32);
 
-    let mut player = player::Player::new(graphics.spawn());
+    let input_map = input::InputMap::load(input::CONFIG_PATH);
+    let mut input = input::InputState::default();
+
+    // Demos start right away, everything else in the main menu
+    let initial: Box<dyn states::State> = if playback.is_some() {
+        Box::new(states::playing::Loading::new())
+    } else {
+        Box::new(states::menus::MainMenu)
+    };
+    let mut session = states::Session {
+        map: options.map.clone(),
+        seed,
+        difficulty: options.difficulty,
+        world: None,
+        playback,
+        recording,
+        respawn: false,
+    };
+    let mut states = states::StateStack::new(
+        &mut states::Context {
+            graphics: &mut graphics,
+            session: &mut session,
+        },
+        initial,
+    );
 
-    let mut pre_time = glfw.get_time();
+    let mut clock = timing::Clock::new(glfw.get_time());
+    let mut limiter =
+        timing::FrameLimiter::new(if vsync { None } else { MAX_FPS }, glfw.get_time());
     // Main loop
     while !window.should_close() {
-        let current_time = glfw.get_time();
-
-        let delta_time = current_time - pre_time;
-        pre_time = current_time;
+        let delta_time = clock.frame(glfw.get_time());
 
+        let mut actions = Vec::new();
         for (_, event) in glfw::flush_messages(&events) {
             match event {
-                glfw::WindowEvent::Key(glfw::Key::Escape, _, glfw::Action::Press, _) => {
-                    // give mouse back
-                    if !paused {
-                        paused = true;
-                        graphics.pause();
-                        window.set_cursor_mode(glfw::CursorMode::Normal);
-                    } else {
-                        paused = false;
-                        graphics.unpause();
-                        window.set_cursor_mode(glfw::CursorMode::Disabled);
-                    }
-                }
-
                 glfw::WindowEvent::Size(width, height) => {
                     graphics.resize(width as u32, height as u32);
                 }
 
-                glfw::WindowEvent::CursorPos(x, y) => {
-                    if !paused {
-                        player.mouse_input(x, y, delta_time);
-                    }
+                glfw::WindowEvent::Focus(false) => {
+                    input.release_all();
                 }
 
-                glfw::WindowEvent::Key(
-                    glfw::Key::F,
-                    _,
-                    glfw::Action::Press,
-                    glfw::Modifiers::Control,
-                ) => {
-                    fill_mode = if fill_mode {
-                        unsafe {
-                            gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
-                        }
-                        false
-                    } else {
-                        unsafe {
-                            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
-                        }
-                        true
-                    };
-                }
+                _ => {}
+            }
+
+            actions.append(&mut input.handle_event(&input_map, &event));
+        }
+        actions.append(&mut input.poll_gamepad(&input_map, &glfw));
 
-                glfw::WindowEvent::Key(
-                    glfw::Key::P,
-                    _,
-                    glfw::Action::Press,
-                    glfw::Modifiers::Control,
-                ) => {
-                    show_fps = if show_fps {
+        let mut ctx = states::Context {
+            graphics: &mut graphics,
+            session: &mut session,
+        };
+        for action in actions {
+            match action {
+                input::Action::ToggleWireframe => ctx.graphics.toggle_wireframe(),
+
+                input::Action::ToggleFps => {
+                    show_fps = !show_fps;
+                    if !show_fps {
                         window.set_title(TITLE);
-                        false
-                    } else {
-                        true
-                    };
+                    }
                 }
 
-                glfw::WindowEvent::Key(
-                    glfw::Key::S,
-                    _,
-                    glfw::Action::Press,
-                    glfw::Modifiers::Control,
-                ) => {
-                    player.toggle_spectator();
+                input::Action::ToggleVsync => {
+                    vsync = !vsync;
+                    glfw.set_swap_interval(if vsync {
+                        glfw::SwapInterval::Sync(1)
+                    } else {
+                        glfw::SwapInterval::None
+                    });
+                    limiter.max_fps = if vsync { None } else { MAX_FPS };
                 }
 
-                glfw::WindowEvent::Key(
-                    glfw::Key::H,
-                    _,
-                    glfw::Action::Press,
-                    glfw::Modifiers::Control,
-                ) => {
-                    player.damage(1, graphics.gui_manager());
-                }
+                _ => states.handle_action(&mut ctx, action),
+            }
+        }
 
-                _ => {}
+        // Update in fixed ticks
+        while clock.tick() {
+            let cmd = input.command(timing::TICK_TIME);
+            states.update(&mut ctx, &cmd);
+            if states.quit() {
+                break;
             }
         }
 
-        // Update
-        player.key_input(&window, delta_time, graphics.map());
-        graphics.update(delta_time as f32, &player.cam_view());
+        if states.quit() {
+            window.set_should_close(true);
+        }
+
+        // The mouse belongs to whichever state is on top
+        let capture = states.captures_cursor();
+        if capture != input.capture_mouse {
+            input.capture_mouse = capture;
+            window.set_cursor_mode(if capture {
+                glfw::CursorMode::Disabled
+            } else {
+                glfw::CursorMode::Normal
+            });
+        }
+
+        // Render between the last two ticks
+        let frame = states::Frame {
+            alpha: clock.alpha(),
+            look: if ctx.session.playback.is_some() {
+                (0.0, 0.0)
+            } else {
+                input.pending_look()
+            },
+        };
+        ctx.graphics.clear();
+        states.render(&mut ctx, &frame);
+        ctx.graphics.render_gui();
 
         if show_fps {
             window.set_title(
                 format!(
-                    "{} | FPS: {:.5} | ms per Frame: {:.7}",
+                    "{} | FPS: {:.5} | ms per Frame: {:.7} | Tick: {}",
                     TITLE,
                     1.0 / delta_time,
-                    delta_time
+                    delta_time,
+                    clock.ticks()
                 )
                 .as_str(),
             );
         }
 
         window.swap_buffers();
+        limiter.wait(&glfw);
         glfw.poll_events();
     }
 
+    if let (Some(recording), Some(path)) = (&session.recording, &options.record) {
+        recording.save(path);
+    }
+    if let Some(playback) = &session.playback {
+        match playback.desync() {
+            Some(tick) => println!("Demo playback desynced at tick {tick}"),
+            None if playback.finished() => println!("Demo playback finished in sync"),
+            None => println!("Demo playback stopped early"),
+        }
+    }
+
     graphics.destroy();
 }
@@ -0,0 +1,378 @@
+use crate::geometry::{distance, Point, Segment};
+use crate::graphics::renderable::mapdata::Map;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+// What has to fit through a gate
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Clearance {
+    pub height: f32,
+    pub max_step: f32, // highest step up
+    pub radius: f64,
+}
+
+type ClearanceKey = (u32, u32, u64);
+
+impl Clearance {
+    fn key(&self) -> ClearanceKey {
+        (
+            self.height.to_bits(),
+            self.max_step.to_bits(),
+            self.radius.to_bits(),
+        )
+    }
+}
+
+// A gate on the way, from the side of the sector it gets entered from
+#[derive(Clone, Copy, Debug)]
+struct Portal {
+    edge: Segment,
+}
+
+impl Portal {
+    fn midpoint(&self) -> Point {
+        self.edge.point_at(0.5)
+    }
+
+    // Keeps the radius away from the corners so paths do not cut them
+    fn shrunk(&self, radius: f64) -> Segment {
+        let length = self.edge.length();
+        if length <= radius * 2.0 {
+            let midpoint = self.midpoint();
+            return Segment::new(midpoint, midpoint);
+        }
+        let t = radius / length;
+        Segment::new(self.edge.point_at(t), self.edge.point_at(1.0 - t))
+    }
+}
+
+// Open list entry, the BinaryHeap is a max heap so the order is reversed
+struct Open {
+    cost: f64, // so far plus the estimate
+    node: (u32, usize),
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+// A* over the sectors with the gate midpoints as waypoints.
+// The gates on the way between two sectors get cached, they do not depend on the points in them
+#[derive(Default)]
+pub struct Pathfinder {
+    cache: HashMap<(u32, u32, ClearanceKey), Option<Vec<Portal>>>, // start, goal sector
+}
+
+impl Pathfinder {
+    // Waypoints after from up to and including to, map coordinates. None if there is no way
+    pub fn find(
+        &mut self,
+        map: &Map,
+        from: Point,
+        to: Point,
+        clearance: Clearance,
+    ) -> Option<Vec<Point>> {
+        let start = map.sector_at(from.0 as f32, from.1 as f32)?;
+        let goal = map.sector_at(to.0 as f32, to.1 as f32)?;
+
+        let portals = self
+            .cache
+            .entry((start, goal, clearance.key()))
+            .or_insert_with(|| search(map, start, goal, clearance))
+            .as_ref()?;
+        Some(smooth(from, to, portals, clearance.radius))
+    }
+
+    // Has to be called when the heights or corners of sectors change
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    pub fn reachable(&mut self, map: &Map, from: Point, to: Point, clearance: Clearance) -> bool {
+        self.find(map, from, to, clearance).is_some()
+    }
+}
+
+fn passable(map: &Map, from: u32, gate: usize, clearance: Clearance) -> bool {
+    let sector = &map.sectors[from as usize];
+    let gate = &sector.gates[gate];
+    let target = &map.sectors[gate.target_sector as usize];
+
+    let corner0 = sector.corners[gate.own as usize];
+    let corner1 = sector.corners[(gate.own as usize + 1) % sector.corners.len()];
+    let width = distance(
+        (corner0.0 as f64, corner0.1 as f64),
+        (corner1.0 as f64, corner1.1 as f64),
+    );
+
+    let opening = sector.ceiling.min(target.ceiling) - sector.floor.max(target.floor);
+    target.ceiling - target.floor > clearance.height
+        && opening > clearance.height
+        && target.floor - sector.floor <= clearance.max_step
+        && width >= clearance.radius * 2.0
+}
+
+fn portal(map: &Map, from: u32, gate: usize) -> Portal {
+    let sector = &map.sectors[from as usize];
+    let own = sector.gates[gate].own as usize;
+    let corner0 = sector.corners[own];
+    let corner1 = sector.corners[(own + 1) % sector.corners.len()];
+    Portal {
+        edge: Segment::new(
+            (corner0.0 as f64, corner0.1 as f64),
+            (corner1.0 as f64, corner1.1 as f64),
+        ),
+    }
+}
+
+// Nodes are gates, given by the sector they get passed from and their index in its gates.
+// Costs go from gate to gate, the way through the start and goal sectors does not count
+fn search(map: &Map, start: u32, goal: u32, clearance: Clearance) -> Option<Vec<Portal>> {
+    if start == goal {
+        return Some(Vec::new());
+    }
+
+    // The last gate is one of the goal, the closest one never overestimates
+    let entries: Vec<Point> = (0..map.sectors[goal as usize].gates.len())
+        .map(|gate| portal(map, goal, gate).midpoint())
+        .collect();
+    let estimate = |position: Point| {
+        entries
+            .iter()
+            .map(|entry| distance(position, *entry))
+            .fold(f64::INFINITY, f64::min)
+    };
+
+    let mut open = BinaryHeap::new();
+    let mut costs: HashMap<(u32, usize), f64> = HashMap::new();
+    let mut came_from: HashMap<(u32, usize), (u32, usize)> = HashMap::new();
+
+    let expand = |open: &mut BinaryHeap<Open>,
+                  costs: &mut HashMap<(u32, usize), f64>,
+                  came_from: &mut HashMap<(u32, usize), (u32, usize)>,
+                  sector: u32,
+                  position: Option<Point>,
+                  cost: f64,
+                  previous: Option<(u32, usize)>| {
+        for gate in 0..map.sectors[sector as usize].gates.len() {
+            if !passable(map, sector, gate, clearance) {
+                continue;
+            }
+
+            let node = (sector, gate);
+            let midpoint = portal(map, sector, gate).midpoint();
+            let cost = cost + position.map_or(0.0, |position| distance(position, midpoint));
+            if costs.get(&node).is_some_and(|known| *known <= cost) {
+                continue;
+            }
+            costs.insert(node, cost);
+            if let Some(previous) = previous {
+                came_from.insert(node, previous);
+            }
+            open.push(Open {
+                cost: cost + estimate(midpoint),
+                node,
+            });
+        }
+    };
+
+    expand(
+        &mut open,
+        &mut costs,
+        &mut came_from,
+        start,
+        None,
+        0.0,
+        None,
+    );
+    while let Some(Open { node, cost: total }) = open.pop() {
+        let cost = costs[&node];
+        let midpoint = portal(map, node.0, node.1).midpoint();
+        // Outdated entry, the node was reached cheaper later on
+        if total > cost + estimate(midpoint) + 1e-9 {
+            continue;
+        }
+
+        let entered = map.sectors[node.0 as usize].gates[node.1].target_sector;
+        if entered == goal {
+            let mut portals = vec![portal(map, node.0, node.1)];
+            let mut current = node;
+            while let Some(previous) = came_from.get(&current) {
+                portals.push(portal(map, previous.0, previous.1));
+                current = *previous;
+            }
+            portals.reverse();
+            return Some(portals);
+        }
+
+        expand(
+            &mut open,
+            &mut costs,
+            &mut came_from,
+            entered,
+            Some(midpoint),
+            cost,
+            Some(node),
+        );
+    }
+
+    None
+}
+
+// Skips waypoints as long as the straight line still goes through all gates in between
+fn smooth(from: Point, to: Point, portals: &[Portal], radius: f64) -> Vec<Point> {
+    let gates: Vec<Segment> = portals.iter().map(|portal| portal.shrunk(radius)).collect();
+    let mut targets: Vec<Point> = gates.iter().map(|gate| gate.point_at(0.5)).collect();
+    targets.push(to);
+
+    let mut path = Vec::new();
+    let mut anchor = from;
+    let mut passed = 0; // gates behind the anchor
+    let mut next = 0;
+    while next < targets.len() {
+        let mut furthest = next;
+        while furthest + 1 < targets.len() {
+            let line = Segment::new(anchor, targets[furthest + 1]);
+            if gates[passed..=furthest]
+                .iter()
+                .all(|gate| line.intersect(gate).is_some())
+            {
+                furthest += 1;
+            } else {
+                break;
+            }
+        }
+
+        path.push(targets[furthest]);
+        anchor = targets[furthest];
+        passed = furthest + 1;
+        next = furthest + 1;
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::renderable::mapdata::tests::test_map;
+
+    const CLEARANCE: Clearance = Clearance {
+        height: 0.65,
+        max_step: 0.25,
+        radius: 0.2,
+    };
+
+    // Three rooms in a row along x, the middle one with the given floor and ceiling
+    fn corridor(floor: f32, ceiling: f32) -> Map {
+        test_map(&format!(
+            r#"[
+                {{ "floor": 0.0, "ceiling": 2.0, "corners": [[0, 0], [2, 0], [2, 2], [0, 2]],
+                  "gates": [{{ "own": 1, "targetSector": 1, "targetGate": 3 }}] }},
+                {{ "floor": {floor}, "ceiling": {ceiling},
+                  "corners": [[2, 0], [4, 0], [4, 2], [2, 2]],
+                  "gates": [{{ "own": 1, "targetSector": 2, "targetGate": 3 }},
+                            {{ "own": 3, "targetSector": 0, "targetGate": 1 }}] }},
+                {{ "floor": 0.0, "ceiling": 2.0, "corners": [[4, 0], [6, 0], [6, 2], [4, 2]],
+                  "gates": [{{ "own": 3, "targetSector": 1, "targetGate": 1 }}] }}
+            ]"#
+        ))
+    }
+
+    // Around a corner, the last room is above the middle one
+    fn corner() -> Map {
+        test_map(
+            r#"[
+                { "floor": 0.0, "ceiling": 2.0, "corners": [[0, 0], [2, 0], [2, 2], [0, 2]],
+                  "gates": [{ "own": 1, "targetSector": 1, "targetGate": 3 }] },
+                { "floor": 0.0, "ceiling": 2.0, "corners": [[2, 0], [4, 0], [4, 2], [2, 2]],
+                  "gates": [{ "own": 2, "targetSector": 2, "targetGate": 0 },
+                            { "own": 3, "targetSector": 0, "targetGate": 1 }] },
+                { "floor": 0.0, "ceiling": 2.0, "corners": [[2, 2], [4, 2], [4, 4], [2, 4]],
+                  "gates": [{ "own": 0, "targetSector": 1, "targetGate": 2 }] },
+                { "floor": 0.0, "ceiling": 2.0, "corners": [[8, 0], [9, 0], [9, 1], [8, 1]] }
+            ]"#,
+        )
+    }
+
+    #[test]
+    fn same_sector() {
+        let mut paths = Pathfinder::default();
+        let path = paths.find(&corner(), (0.5, 0.5), (1.5, 1.5), CLEARANCE);
+        assert_eq!(path, Some(vec![(1.5, 1.5)]));
+    }
+
+    // The first gate gets skipped, the line to the second one still goes through it
+    #[test]
+    fn two_gates() {
+        let mut paths = Pathfinder::default();
+        let path = paths.find(&corner(), (0.5, 1.0), (3.0, 3.5), CLEARANCE);
+        assert_eq!(path, Some(vec![(3.0, 2.0), (3.0, 3.5)]));
+    }
+
+    #[test]
+    fn straight_line_skips_waypoints() {
+        let mut paths = Pathfinder::default();
+        let path = paths.find(&corridor(0.0, 2.0), (0.5, 1.0), (5.5, 1.0), CLEARANCE);
+        assert_eq!(path, Some(vec![(5.5, 1.0)]));
+    }
+
+    #[test]
+    fn gate_too_low() {
+        let mut paths = Pathfinder::default();
+        let map = corridor(0.0, 0.5);
+        assert!(!paths.reachable(&map, (0.5, 1.0), (5.5, 1.0), CLEARANCE));
+    }
+
+    #[test]
+    fn step_too_high() {
+        let mut paths = Pathfinder::default();
+        let map = corridor(0.5, 2.0);
+        assert!(!paths.reachable(&map, (0.5, 1.0), (5.5, 1.0), CLEARANCE));
+
+        let climber = Clearance {
+            max_step: 0.5,
+            ..CLEARANCE
+        };
+        assert!(paths.reachable(&map, (0.5, 1.0), (5.5, 1.0), climber));
+    }
+
+    #[test]
+    fn unreachable() {
+        let mut paths = Pathfinder::default();
+        let map = corner();
+        // A room without gates and a point outside of the map
+        assert!(!paths.reachable(&map, (0.5, 1.0), (8.5, 0.5), CLEARANCE));
+        assert!(!paths.reachable(&map, (0.5, 1.0), (6.0, 0.5), CLEARANCE));
+    }
+
+    // Other points between the same sectors get their own path, not the cached one
+    #[test]
+    fn cache_keeps_points_apart() {
+        let map = corner();
+        let mut paths = Pathfinder::default();
+        paths.find(&map, (0.5, 1.0), (3.0, 3.5), CLEARANCE);
+        let cached = paths.find(&map, (1.5, 0.5), (3.5, 2.5), CLEARANCE);
+        let fresh = Pathfinder::default().find(&map, (1.5, 0.5), (3.5, 2.5), CLEARANCE);
+        assert_eq!(cached, fresh);
+        assert_eq!(cached.unwrap().last(), Some(&(3.5, 2.5)));
+    }
+}
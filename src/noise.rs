@@ -0,0 +1,31 @@
+use crate::graphics::renderable::mapdata::Map;
+use std::collections::VecDeque;
+
+// Flood fill from the sector of the noise through open gates, sound blocking gates stop it.
+// Returns which sectors heard it
+pub fn propagate(map: &Map, origin: u32) -> Vec<bool> {
+    let mut heard = vec![false; map.sectors.len()];
+    let mut queue = VecDeque::from([origin]);
+    heard[origin as usize] = true;
+
+    while let Some(index) = queue.pop_front() {
+        let sector = &map.sectors[index as usize];
+        for gate in &sector.gates {
+            let target = &map.sectors[gate.target_sector as usize];
+            // Either side of the gate can block
+            let blocked = gate.blocks_sound
+                || target
+                    .gates
+                    .iter()
+                    .any(|back| back.own == gate.target_gate && back.blocks_sound);
+            let open = sector.ceiling.min(target.ceiling) > sector.floor.max(target.floor);
+            if heard[gate.target_sector as usize] || !open || blocked {
+                continue;
+            }
+
+            heard[gate.target_sector as usize] = true;
+            queue.push_back(gate.target_sector);
+        }
+    }
+    heard
+}
@@ -1,147 +1,220 @@
+use crate::actors::{step, Actor, ActorId, ActorKind, Actors, Step};
+use crate::damage::{Damage, DamageType, Vitals};
 use crate::graphics::camera::{Camera, UP};
 use crate::graphics::renderable::mapdata::Map;
-use crate::utils::Line;
+use crate::input::{Action, TickCommand};
+use crate::inventory::{Inventory, ItemDef, ItemKind, Powerup};
+use crate::weapons::Weapons;
 use nalgebra_glm as ng;
 
-pub const CAMERA_SENSITIVITY: f64 = 7.0;
-
 const FORWARD_SPEED: f64 = 1.0;
 const BACK_SPEED: f64 = 0.5;
 const STRAVE_SPEED: f64 = 0.75;
 const FLY_SPEED: f64 = 1.0;
 const SPEED_FAC: f64 = 2.0;
 
-const PLAYER_HEIGHT: f32 = 0.65;
+pub const PLAYER_HEIGHT: f32 = 0.65;
+pub const PLAYER_RADIUS: f64 = 0.2;
+const DEAD_HEIGHT: f32 = 0.1; // eye height of a dead player
+const DEATH_DROP_TIME: f64 = 0.5;
+
+pub const MAX_HEALTH: i32 = 5;
+const MAX_OVERHEAL: i32 = 8;
+const OVERHEAL_DECAY: f64 = 1.0; // seconds per point above MAX_HEALTH
+pub const MAX_ARMOR: i32 = 5;
+
+const SAFE_FALL: f32 = 0.5; // height which can be dropped without damage
+const FALL_DAMAGE_HEIGHT: f32 = 0.5; // one point of damage per this much height above SAFE_FALL
+const ENVIRONMENT_INTERVAL: f64 = 1.0; // seconds between damage by the floor
 
 pub struct Player {
     camera: Camera,
     spectator: bool,
     grounded: bool,
-    health: i32, // max. 5
+    actor: ActorId, // body and vitals, the camera is the eye of it
+    overheal_time: f64,
+    environment_time: f64,
+    death: Option<(f64, f64)>, // seconds since death, eye height when dying
+    checkpoint: Option<((f32, f32, f32), f32)>, // with the angle it was reached at
+    pub weapons: Weapons,
+    pub inventory: Inventory,
 
-    cur_sector: u32,
     next_pos: (f64, f64),
+    prev_pos: ng::DVec3, // position at the start of the last tick, for render interpolation
+    speed: f64,          // along the floor in the last tick, units per second
+    // Sector and edge of the gate gone through in the last tick
+    crossed: Option<(u32, usize)>,
 }
 
 impl Player {
-    pub fn new(spawn: (f32, f32, f32)) -> Self {
+    // The angle is in map degrees, see Thing. The body gets spawned into the actors
+    pub fn new(
+        spawn: (f32, f32, f32),
+        angle: f32,
+        map: &Map,
+        weapons: Weapons,
+        inventory: Inventory,
+        actors: &mut Actors,
+    ) -> Self {
+        let mut actor = Actor::new(
+            ActorKind::Player,
+            ng::DVec3::new(spawn.0 as f64, spawn.1 as f64, spawn.2 as f64),
+            map.sector_at(spawn.0, spawn.2).unwrap_or(0),
+            PLAYER_RADIUS,
+            PLAYER_HEIGHT,
+        );
+        actor.vitals = Vitals::new(MAX_HEALTH);
+        actor.shootable = true;
+
         let mut player = Player {
             camera: Camera::new(),
             spectator: false,
             grounded: false,
-            health: 5,
+            actor: actors.spawn(actor),
+            overheal_time: 0.0,
+            environment_time: 0.0,
+            death: None,
+            checkpoint: None,
+            weapons,
+            inventory,
 
-            cur_sector: 0,
             next_pos: (spawn.0 as f64, spawn.2 as f64 * -1.0),
+            prev_pos: ng::DVec3::zeros(),
+            speed: 0.0,
+            crossed: None,
         };
 
         player.camera.put(
             spawn.0 as f64,
             (PLAYER_HEIGHT + spawn.1) as f64,
             -1.0 * spawn.2 as f64,
-            0.0,
+            -angle as f64,
             0.0,
         );
+        player.prev_pos = *player.camera.pos();
 
         player
     }
 
-    pub fn cam_view(&self) -> ng::Mat4 {
-        self.camera.view().cast()
+    pub fn cam_view(&self, alpha: f64, look: (f64, f64)) -> ng::Mat4 {
+        self.camera
+            .view_interpolated(&self.prev_pos, alpha, look)
+            .cast()
+    }
+
+    pub fn position(&self) -> ng::DVec3 {
+        self.camera.position()
     }
 
-    pub fn mouse_input(&mut self, x: f64, y: f64, delta_time: f64) {
-        self.camera.cur_mov(x, y, delta_time * CAMERA_SENSITIVITY);
+    // Eye position in map coordinates
+    pub fn eye(&self) -> ng::DVec3 {
+        let pos = self.camera.position();
+        ng::DVec3::new(pos.x, pos.y, -pos.z)
     }
 
-    pub fn key_input(&mut self, window: &glfw::Window, delta_time: f64, map: &Map) {
+    // yaw, pitch in degrees
+    pub fn aim(&self) -> (f64, f64) {
+        self.camera.angles()
+    }
+
+    pub fn id(&self) -> ActorId {
+        self.actor
+    }
+
+    // The body is never removed from the actors
+    pub fn body<'a>(&self, actors: &'a Actors) -> &'a Actor {
+        actors.get(self.actor).expect("The player has no body")
+    }
+
+    fn body_mut<'a>(&self, actors: &'a mut Actors) -> &'a mut Actor {
+        actors.get_mut(self.actor).expect("The player has no body")
+    }
+
+    pub fn sector(&self, actors: &Actors) -> u32 {
+        self.body(actors).sector
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn crossed(&self) -> Option<(u32, usize)> {
+        self.crossed
+    }
+
+    pub fn update(&mut self, cmd: &TickCommand, delta_time: f64, map: &Map, actors: &mut Actors) {
+        self.prev_pos = *self.camera.pos();
+        self.speed = 0.0;
+        self.crossed = None;
+
+        // Dead players do not take input, the camera drops to the floor
+        let floor = map.sectors[self.sector(actors) as usize].floor;
+        if let Some((time, height)) = &mut self.death {
+            *time += delta_time;
+            let t = (*time / DEATH_DROP_TIME).min(1.0);
+            self.camera
+                .put_y(*height + ((floor + DEAD_HEIGHT) as f64 - *height) * t);
+            return;
+        }
+
+        self.inventory.update(delta_time);
+
+        // Overheal wears off over time
+        let vitals = &mut self.body_mut(actors).vitals;
+        if vitals.health > MAX_HEALTH {
+            self.overheal_time += delta_time;
+            if self.overheal_time >= OVERHEAL_DECAY {
+                self.overheal_time -= OVERHEAL_DECAY;
+                vitals.health -= 1;
+            }
+        } else {
+            self.overheal_time = 0.0;
+        }
+
+        self.camera.rotate(cmd.yaw, cmd.pitch);
+
         let xz_front = &self.camera.xz_front();
         let cur_pos = (self.camera.pos().x, self.camera.pos().z);
 
         let mut mov_change = ng::DVec3::zeros();
+        let mut fall = 0;
 
-        if window.get_key(glfw::Key::W) == glfw::Action::Press {
-            mov_change += xz_front
-                * delta_time
-                * if self.spectator {
-                    FLY_SPEED
-                } else {
-                    FORWARD_SPEED
-                        * if (window.get_key(glfw::Key::D) == glfw::Action::Press)
-                            ^ (window.get_key(glfw::Key::A) == glfw::Action::Press)
-                        {
-                            0.5
-                        } else {
-                            1.0
-                        }
-                }
-                * SPEED_FAC;
-        }
+        // Analog input scales the speed
+        let (mut forward, mut right) = (cmd.forward, cmd.right);
+        let xz_right = ng::normalize(&ng::cross(xz_front, &UP));
+        if self.spectator {
+            mov_change +=
+                (xz_front * forward + xz_right * right) * delta_time * FLY_SPEED * SPEED_FAC;
+        } else {
+            // Walking diagonally with the keys is not faster
+            let keys = |a: Action, b: Action| cmd.held(a) ^ cmd.held(b);
+            if keys(Action::MoveForward, Action::MoveBackward)
+                && keys(Action::StrafeRight, Action::StrafeLeft)
+            {
+                forward *= 0.5;
+                right *= 0.5;
+            }
 
-        if window.get_key(glfw::Key::S) == glfw::Action::Press {
             mov_change += xz_front
+                * forward
                 * delta_time
-                * if self.spectator {
-                    FLY_SPEED
+                * if forward > 0.0 {
+                    FORWARD_SPEED
                 } else {
                     BACK_SPEED
-                        * if (window.get_key(glfw::Key::D) == glfw::Action::Press)
-                            ^ (window.get_key(glfw::Key::A) == glfw::Action::Press)
-                        {
-                            0.5
-                        } else {
-                            1.0
-                        }
-                }
-                * SPEED_FAC
-                * -1.0;
-        }
-
-        if window.get_key(glfw::Key::D) == glfw::Action::Press {
-            mov_change += ng::normalize(&ng::cross(xz_front, &UP))
-                * delta_time
-                * if self.spectator {
-                    FLY_SPEED
-                } else {
-                    STRAVE_SPEED
-                        * if (window.get_key(glfw::Key::W) == glfw::Action::Press)
-                            ^ (window.get_key(glfw::Key::S) == glfw::Action::Press)
-                        {
-                            0.5
-                        } else {
-                            1.0
-                        }
                 }
                 * SPEED_FAC;
+            mov_change += xz_right * right * delta_time * STRAVE_SPEED * SPEED_FAC;
         }
 
-        if window.get_key(glfw::Key::A) == glfw::Action::Press {
-            mov_change += ng::normalize(&ng::cross(xz_front, &UP))
-                * delta_time
-                * if self.spectator {
-                    FLY_SPEED
-                } else {
-                    STRAVE_SPEED
-                        * if (window.get_key(glfw::Key::W) == glfw::Action::Press)
-                            ^ (window.get_key(glfw::Key::S) == glfw::Action::Press)
-                        {
-                            0.5
-                        } else {
-                            1.0
-                        }
-                }
-                * SPEED_FAC
-                * -1.0;
-        }
-
-        if window.get_key(glfw::Key::Space) == glfw::Action::Press {
+        if cmd.held(Action::Jump) {
             if self.spectator {
                 mov_change += UP * delta_time * FLY_SPEED * SPEED_FAC;
             } else if self.grounded {
                 self.jump();
             }
         }
-        if window.get_key(glfw::Key::LeftShift) == glfw::Action::Press {
+        if cmd.held(Action::FlyDown) {
             if self.spectator {
                 mov_change += UP * delta_time * FLY_SPEED * SPEED_FAC * -1.0;
             }
@@ -154,67 +227,199 @@ impl Player {
                     (*self.camera.pos() + mov_change).z,
                 );
 
-                let sector = &map.sectors[self.cur_sector as usize];
-                for i in 0..sector.corners.len() {
-                    let corner0 = &sector.corners[i];
-                    let corner1 = &sector.corners[if i + 1 == sector.corners.len() {
-                        0
-                    } else {
-                        i + 1
-                    }];
-
-                    if Line::new_tuples(cur_pos, self.next_pos).crosses(&Line::new(
-                        corner0.0 as f64,
-                        corner0.1 as f64 * -1.0,
-                        corner1.0 as f64,
-                        corner1.1 as f64 * -1.0,
-                    )) {
-                        // Check if collided with a gate
-                        let mut is_gate = false;
-                        for gate in &sector.gates {
-                            if i == gate.own as usize {
-                                is_gate = true;
-                                let entering = &map.sectors[gate.target_sector as usize];
-                                if entering.ceiling - entering.floor > PLAYER_HEIGHT {
-                                    self.cur_sector = gate.target_sector;
-                                } else {
-                                    // Cancel Move
-                                    mov_change = ng::DVec3::zeros();
-                                }
-                                break;
-                            }
-                        }
-
-                        if !is_gate {
-                            mov_change = ng::DVec3::zeros();
+                // in map coordinates
+                let from = (cur_pos.0, -cur_pos.1);
+                let to = (self.next_pos.0, -self.next_pos.1);
+                let body = self.body_mut(actors);
+                match step(map, body.sector, from, to, PLAYER_HEIGHT) {
+                    Step::Inside => {}
+                    Step::Gate { sector, edge, drop } => {
+                        if drop > SAFE_FALL {
+                            fall = ((drop - SAFE_FALL) / FALL_DAMAGE_HEIGHT).ceil() as i32;
                         }
-                        break;
+                        self.crossed = Some((body.sector, edge));
+                        body.sector = sector;
                     }
+                    // Cancel Move
+                    Step::Blocked => mov_change = ng::DVec3::zeros(),
                 }
-
-                let sector = &map.sectors[self.cur_sector as usize];
-                // Put onto ground
-                self.camera.put_y((sector.floor + PLAYER_HEIGHT) as f64);
             }
 
             *self.camera.pos() += mov_change; // Move
+            self.speed =
+                (mov_change.x * mov_change.x + mov_change.z * mov_change.z).sqrt() / delta_time;
+            self.sync_actor(actors);
+        }
+
+        // Put onto ground, every tick since floors can move
+        if !self.spectator {
+            let sector = &map.sectors[self.sector(actors) as usize];
+            self.camera.put_y((sector.floor + PLAYER_HEIGHT) as f64);
+            self.sync_actor(actors);
+        }
+
+        if fall > 0 {
+            self.damage(Damage::new(fall, DamageType::Fall), actors);
+        }
+        self.hurt_by_floor(delta_time, map, actors);
+    }
+
+    // Damaging floors hurt in intervals, starting right when stepping onto them
+    fn hurt_by_floor(&mut self, delta_time: f64, map: &Map, actors: &mut Actors) {
+        let floor_damage = map.sectors[self.sector(actors) as usize].damage;
+        if self.spectator || self.dead() || floor_damage.is_none() {
+            self.environment_time = 0.0;
+            return;
+        }
+
+        if self.environment_time <= 0.0 {
+            self.damage(floor_damage.unwrap(), actors);
+            self.environment_time += ENVIRONMENT_INTERVAL;
         }
+        self.environment_time -= delta_time;
     }
 
     fn jump(&mut self) {}
 
-    pub fn toggle_spectator(&mut self) {
-        self.spectator = !self.spectator;
+    pub fn toggle_spectator(&mut self, map: &Map, actors: &mut Actors) {
+        if !self.spectator {
+            self.spectator = true;
+            return;
+        }
+
+        // Stay in spectator mode while outside of the map
+        let pos = *self.camera.pos();
+        if self.teleport(pos.x, -pos.z, map, actors) {
+            self.spectator = false;
+        }
+    }
+
+    // x and z in map coordinates, returns false if the position is not inside a sector
+    pub fn teleport(&mut self, x: f64, z: f64, map: &Map, actors: &mut Actors) -> bool {
+        let Some(sector) = map.sector_at(x as f32, z as f32) else {
+            return false;
+        };
+
+        self.body_mut(actors).sector = sector;
+        self.next_pos = (x, -z);
+        self.camera.put_xz(x, -z);
+        self.camera
+            .put_y((map.sectors[sector as usize].floor + PLAYER_HEIGHT) as f64);
+        self.prev_pos = *self.camera.pos();
+        self.sync_actor(actors);
+
+        true
     }
 
-    pub fn damage(&mut self, damage: i32, gui: &crate::graphics::guimanager::GuiManager) {
-        self.health -= damage;
+    // The actor stands below the camera
+    fn sync_actor(&self, actors: &mut Actors) {
+        let eye = self.eye();
+        self.body_mut(actors).position = eye - ng::DVec3::new(0.0, PLAYER_HEIGHT as f64, 0.0);
+    }
 
-        // Update Healthbar
-        gui.set_hp(if self.health >= 0 {
-            self.health as u32
-        } else {
-            0
-        });
+    pub fn damage(&mut self, damage: Damage, actors: &mut Actors) {
+        if self.dead() || self.inventory.has_powerup(Powerup::Invulnerability) {
+            return;
+        }
+
+        let body = self.body_mut(actors);
+        body.damage(damage);
+        if body.dead() {
+            self.spectator = false;
+            self.death = Some((0.0, self.camera.position().y));
+        }
+    }
+
+    // Overheal allows to go above MAX_HEALTH, returns false if nothing was healed
+    pub fn heal(&mut self, amount: i32, overheal: bool, actors: &mut Actors) -> bool {
+        let limit = if overheal { MAX_OVERHEAL } else { MAX_HEALTH };
+        let vitals = &mut self.body_mut(actors).vitals;
+        if self.dead() || vitals.health >= limit {
+            return false;
+        }
+
+        vitals.health = (vitals.health + amount).min(limit);
+        true
+    }
+
+    // Better armor replaces the absorption, returns false if nothing was picked up
+    pub fn add_armor(&mut self, amount: i32, absorption: f64, actors: &mut Actors) -> bool {
+        let vitals = &mut self.body_mut(actors).vitals;
+        if self.dead() || vitals.armor >= MAX_ARMOR {
+            return false;
+        }
+
+        vitals.armor = (vitals.armor + amount).min(MAX_ARMOR);
+        vitals.absorption = vitals.absorption.max(absorption);
+        true
+    }
+
+    // Returns false if the item was of no use, it stays where it is then
+    pub fn pick_up(&mut self, item: &ItemDef, actors: &mut Actors) -> bool {
+        if self.dead() {
+            return false;
+        }
+
+        match &item.kind {
+            ItemKind::Health { amount, overheal } => self.heal(*amount, *overheal, actors),
+            ItemKind::Armor { amount, absorption } => self.add_armor(*amount, *absorption, actors),
+            ItemKind::Ammo { ammo, amount } => self.inventory.add_ammo(ammo, *amount),
+            ItemKind::Key(key) => self.inventory.add_key(key),
+            ItemKind::Powerup { powerup, duration } => {
+                self.inventory.add_powerup(*powerup, *duration);
+                true
+            }
+        }
+    }
+
+    pub fn dead(&self) -> bool {
+        self.death.is_some()
+    }
+
+    pub fn spectator(&self) -> bool {
+        self.spectator
+    }
+
+    // The death camera reached the floor
+    pub fn death_finished(&self) -> bool {
+        matches!(self.death, Some((time, _)) if time >= DEATH_DROP_TIME)
+    }
+
+    // Remembers which way the player was facing as well.
+    // Returns false if the checkpoint was already active
+    pub fn set_checkpoint(&mut self, checkpoint: (f32, f32, f32)) -> bool {
+        if matches!(self.checkpoint, Some((point, _)) if point == checkpoint) {
+            return false;
+        }
+        self.checkpoint = Some((checkpoint, -self.aim().0 as f32));
+        true
+    }
+
+    // Back to life at the last checkpoint or the spawn of the map
+    pub fn respawn(&mut self, map: &Map, actors: &mut Actors) {
+        let (point, angle) = self.checkpoint.unwrap_or((map.spawn, map.spawn_angle));
+
+        self.body_mut(actors).vitals = Vitals::new(MAX_HEALTH);
+        self.overheal_time = 0.0;
+        self.environment_time = 0.0;
+        self.death = None;
+        self.spectator = false;
+
+        self.teleport(point.0 as f64, point.2 as f64, map, actors);
+        self.face(angle);
+    }
+
+    // Map degrees like the spawn angle, looking straight ahead
+    pub fn face(&mut self, angle: f32) {
+        let pos = self.camera.position();
+        self.camera.put(pos.x, pos.y, pos.z, -angle as f64, 0.0);
+    }
+
+    pub fn health(&self, actors: &Actors) -> i32 {
+        self.body(actors).vitals.health
+    }
+
+    pub fn armor(&self, actors: &Actors) -> i32 {
+        self.body(actors).vitals.armor
     }
 }
@@ -0,0 +1,107 @@
+use super::playing::Loading;
+use super::{render_world, Context, Frame, State, Transition};
+use crate::graphics::guimanager::ActiveInterface;
+use crate::input::Action;
+
+pub struct MainMenu;
+
+impl State for MainMenu {
+    fn interface(&self) -> ActiveInterface {
+        ActiveInterface::MENU
+    }
+
+    fn handle_action(&mut self, _ctx: &mut Context, action: Action) -> Transition {
+        match action {
+            Action::MenuConfirm => Transition::Switch(Box::new(Loading::new())),
+            Action::Pause => Transition::Quit,
+            _ => Transition::None,
+        }
+    }
+
+    fn render(&mut self, ctx: &mut Context, frame: &Frame) {
+        render_world(ctx, frame, true);
+    }
+}
+
+pub struct Paused;
+
+impl State for Paused {
+    fn interface(&self) -> ActiveInterface {
+        ActiveInterface::PAUSE
+    }
+
+    fn handle_action(&mut self, _ctx: &mut Context, action: Action) -> Transition {
+        match action {
+            Action::Pause | Action::MenuConfirm => Transition::Pop,
+            Action::Help => Transition::Push(Box::new(Help)),
+            _ => Transition::None,
+        }
+    }
+
+    fn render(&mut self, ctx: &mut Context, frame: &Frame) {
+        render_world(ctx, frame, true);
+    }
+}
+
+pub struct Help;
+
+impl State for Help {
+    fn interface(&self) -> ActiveInterface {
+        ActiveInterface::HELP
+    }
+
+    fn handle_action(&mut self, _ctx: &mut Context, action: Action) -> Transition {
+        match action {
+            Action::Pause | Action::Help | Action::MenuConfirm => Transition::Pop,
+            _ => Transition::None,
+        }
+    }
+
+    fn render(&mut self, ctx: &mut Context, frame: &Frame) {
+        render_world(ctx, frame, true);
+    }
+}
+
+// The level time is set on the interface when the level finishes
+pub struct Intermission;
+
+impl State for Intermission {
+    fn interface(&self) -> ActiveInterface {
+        ActiveInterface::INTERMISSION
+    }
+
+    fn handle_action(&mut self, _ctx: &mut Context, action: Action) -> Transition {
+        match action {
+            Action::MenuConfirm | Action::Pause => Transition::Reset(Box::new(MainMenu)),
+            _ => Transition::None,
+        }
+    }
+
+    fn render(&mut self, ctx: &mut Context, frame: &Frame) {
+        render_world(ctx, frame, true);
+    }
+}
+
+// Confirming lets the player respawn with the next tick
+pub struct GameOver;
+
+impl State for GameOver {
+    fn interface(&self) -> ActiveInterface {
+        ActiveInterface::GAMEOVER
+    }
+
+    fn handle_action(&mut self, ctx: &mut Context, action: Action) -> Transition {
+        match action {
+            Action::MenuConfirm => {
+                ctx.session.respawn = true;
+                Transition::Pop
+            }
+            Action::Pause => Transition::Reset(Box::new(MainMenu)),
+            _ => Transition::None,
+        }
+    }
+
+    fn render(&mut self, ctx: &mut Context, frame: &Frame) {
+        render_world(ctx, frame, true);
+    }
+}
@@ -0,0 +1,145 @@
+pub mod menus;
+pub mod playing;
+
+use crate::demo::{Demo, Playback};
+use crate::graphics::guimanager::ActiveInterface;
+use crate::graphics::Graphics;
+use crate::input::{Action, TickCommand};
+use crate::things::Difficulty;
+use crate::world::World;
+
+// Everything a game session consists of, shared between the states
+pub struct Session {
+    pub map: String,
+    pub seed: u64,
+    pub difficulty: Difficulty,
+    pub world: Option<World>,
+    pub playback: Option<Playback>,
+    pub recording: Option<Demo>,
+    pub respawn: bool, // with the next tick
+}
+
+pub struct Context<'a> {
+    pub graphics: &'a mut Graphics,
+    pub session: &'a mut Session,
+}
+
+// Per frame render information
+pub struct Frame {
+    pub alpha: f64,       // between the last two ticks
+    pub look: (f64, f64), // mouse look the simulation has not seen yet
+}
+
+pub enum Transition {
+    None,
+    Push(Box<dyn State>),
+    Pop,
+    Switch(Box<dyn State>), // replaces the current state
+    Reset(Box<dyn State>),  // replaces the whole stack
+    Quit,
+}
+
+pub trait State {
+    fn interface(&self) -> ActiveInterface;
+
+    fn captures_cursor(&self) -> bool {
+        false
+    }
+
+    fn handle_action(&mut self, _ctx: &mut Context, _action: Action) -> Transition {
+        Transition::None
+    }
+
+    // Called once per tick
+    fn update(&mut self, _ctx: &mut Context, _cmd: &TickCommand) -> Transition {
+        Transition::None
+    }
+
+    fn render(&mut self, ctx: &mut Context, frame: &Frame);
+}
+
+// Draws the world as seen by the player, if there is one
+pub fn render_world(ctx: &mut Context, frame: &Frame, dimmed: bool) {
+    if let Some(world) = &mut ctx.session.world {
+        ctx.graphics.update_map(&mut world.map);
+        let weapon = world.player.weapons.view().filter(|_| !world.player.dead());
+        let light = world.map.sectors[world.player.sector(&world.actors) as usize].light;
+        ctx.graphics
+            .set_flash(weapon.as_ref().map_or(0.0, |weapon| weapon.light as f32));
+
+        let view = world.player.cam_view(frame.alpha, frame.look);
+        ctx.graphics.render_map(&world.map, &view, dimmed);
+        ctx.graphics.render_sprites(&world.sprites(), &view, dimmed);
+        if let Some(weapon) = &weapon {
+            ctx.graphics.render_weapon(weapon, light, dimmed);
+        }
+    }
+}
+
+// Only the topmost state gets input, updates and renders
+pub struct StateStack {
+    states: Vec<Box<dyn State>>,
+    quit: bool,
+}
+
+impl StateStack {
+    pub fn new(ctx: &mut Context, initial: Box<dyn State>) -> Self {
+        let mut stack = StateStack {
+            states: Vec::new(),
+            quit: false,
+        };
+        stack.apply(ctx, Transition::Push(initial));
+        stack
+    }
+
+    fn top(&mut self) -> &mut Box<dyn State> {
+        self.states.last_mut().expect("State stack is empty")
+    }
+
+    pub fn handle_action(&mut self, ctx: &mut Context, action: Action) {
+        let transition = self.top().handle_action(ctx, action);
+        self.apply(ctx, transition);
+    }
+
+    pub fn update(&mut self, ctx: &mut Context, cmd: &TickCommand) {
+        let transition = self.top().update(ctx, cmd);
+        self.apply(ctx, transition);
+    }
+
+    pub fn render(&mut self, ctx: &mut Context, frame: &Frame) {
+        self.top().render(ctx, frame);
+    }
+
+    pub fn captures_cursor(&mut self) -> bool {
+        self.top().captures_cursor()
+    }
+
+    pub fn quit(&self) -> bool {
+        self.quit
+    }
+
+    fn apply(&mut self, ctx: &mut Context, transition: Transition) {
+        match transition {
+            Transition::None => return,
+            Transition::Push(state) => self.states.push(state),
+            Transition::Pop if self.states.len() > 1 => {
+                self.states.pop();
+            }
+            Transition::Switch(state) => {
+                self.states.pop();
+                self.states.push(state);
+            }
+            Transition::Reset(state) => {
+                self.states.clear();
+                self.states.push(state);
+            }
+            Transition::Pop | Transition::Quit => {
+                self.quit = true;
+                return;
+            }
+        }
+
+        let interface = self.top().interface();
+        ctx.graphics.set_interface(interface);
+    }
+}
@@ -0,0 +1,142 @@
+use super::menus::{GameOver, Help, Intermission, Paused};
+use super::{render_world, Context, Frame, State, Transition};
+use crate::graphics::guimanager::ActiveInterface;
+use crate::input::{Action, TickCommand};
+use crate::timing::TICK_RATE;
+use crate::world::{Event, World};
+
+// Loads the session's map after the loading screen has been shown once
+pub struct Loading {
+    shown: bool,
+}
+
+impl Loading {
+    pub fn new() -> Self {
+        Loading { shown: false }
+    }
+}
+
+impl State for Loading {
+    fn interface(&self) -> ActiveInterface {
+        ActiveInterface::NONE
+    }
+
+    fn update(&mut self, ctx: &mut Context, _cmd: &TickCommand) -> Transition {
+        if !self.shown {
+            return Transition::None;
+        }
+
+        let mut world = World::load(&ctx.session.map, ctx.session.seed, ctx.session.difficulty);
+        ctx.graphics.upload_map(&mut world.map);
+
+        let playing = Playing::new(
+            world.player.health(&world.actors),
+            world.player.armor(&world.actors),
+        );
+        ctx.graphics
+            .gui_manager()
+            .set_hp(playing.health.max(0) as u32);
+        ctx.graphics
+            .gui_manager()
+            .set_armor(playing.armor.max(0) as u32);
+        ctx.session.world = Some(world);
+
+        Transition::Switch(Box::new(playing))
+    }
+
+    fn render(&mut self, _ctx: &mut Context, _frame: &Frame) {
+        self.shown = true;
+    }
+}
+
+pub struct Playing {
+    // shown on the HUD
+    health: i32,
+    armor: i32,
+}
+
+impl Playing {
+    pub fn new(health: i32, armor: i32) -> Self {
+        Playing { health, armor }
+    }
+}
+
+impl State for Playing {
+    fn interface(&self) -> ActiveInterface {
+        ActiveInterface::TEST
+    }
+
+    fn captures_cursor(&self) -> bool {
+        true
+    }
+
+    fn handle_action(&mut self, _ctx: &mut Context, action: Action) -> Transition {
+        match action {
+            Action::Pause => Transition::Push(Box::new(Paused)),
+            Action::Help => Transition::Push(Box::new(Help)),
+            _ => Transition::None,
+        }
+    }
+
+    fn update(&mut self, ctx: &mut Context, cmd: &TickCommand) -> Transition {
+        let session = &mut *ctx.session;
+        let Some(world) = &mut session.world else {
+            return Transition::None;
+        };
+
+        let mut cmd = match &mut session.playback {
+            Some(playback) => match playback.next_command() {
+                Some(cmd) => cmd,
+                None => return Transition::Quit,
+            },
+            None => *cmd,
+        };
+        // Goes through the command so recordings contain it
+        if std::mem::take(&mut session.respawn) {
+            cmd.held |= Action::Respawn.bit();
+            cmd.pressed |= Action::Respawn.bit();
+        }
+
+        world.tick(&cmd);
+        for event in world.events() {
+            if let Event::Pickup(message) = event {
+                println!("{message}");
+            }
+        }
+
+        let state = world.state();
+        if let Some(playback) = &mut session.playback {
+            playback.check(state.into());
+        }
+        if let Some(recording) = &mut session.recording {
+            recording.record(&cmd, state.into());
+        }
+
+        if world.player.health(&world.actors) != self.health {
+            self.health = world.player.health(&world.actors);
+            ctx.graphics.gui_manager().set_hp(self.health.max(0) as u32);
+        }
+        if world.player.armor(&world.actors) != self.armor {
+            self.armor = world.player.armor(&world.actors);
+            ctx.graphics
+                .gui_manager()
+                .set_armor(self.armor.max(0) as u32);
+        }
+
+        // Demos contain the respawn themselves
+        if world.player.death_finished() && session.playback.is_none() {
+            Transition::Push(Box::new(GameOver))
+        } else if world.finished() {
+            ctx.graphics
+                .gui_manager()
+                .set_time(state.tick as f64 / TICK_RATE);
+            Transition::Switch(Box::new(Intermission))
+        } else {
+            Transition::None
+        }
+    }
+
+    fn render(&mut self, ctx: &mut Context, frame: &Frame) {
+        render_world(ctx, frame, false);
+    }
+}
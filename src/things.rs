@@ -0,0 +1,180 @@
+use crate::geometry::point_in_polygon;
+use crate::graphics::renderable::mapdata::Map;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        Difficulty::ALL
+            .into_iter()
+            .find(|difficulty| difficulty.name() == name)
+    }
+
+    pub fn bit(&self) -> u8 {
+        1 << *self as u8
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ThingKind {
+    PlayerStart,
+    TeleportDestination, // where teleport triggers into its sector lead
+    Enemy(String),       // enemy type
+    Item {
+        item: String, // name in the item list
+        respawn: Option<f32>,
+    },
+    Decoration(String), // sprite
+    Light {
+        color: (f32, f32, f32),
+        intensity: f32,
+        radius: f32,
+    },
+    SoundEmitter {
+        sound: String,
+        radius: f32,
+        looping: bool,
+    },
+    Trigger {
+        action: String,
+        radius: f32,
+    },
+}
+
+// Something placed in the map, everything in map coordinates
+#[derive(Clone, Debug)]
+pub struct Thing {
+    pub kind: ThingKind,
+    pub position: (f32, f32, f32),
+    pub angle: f32,                  // degrees, 0 looks along x, 90 along z
+    pub sector: u32,                 // the sector the thing is in
+    pub difficulty: u8,              // bit per difficulty it appears on
+    pub properties: json::JsonValue, // the whole entry, for anything not covered by the kind
+}
+
+impl Thing {
+    pub fn appears_on(&self, difficulty: Difficulty) -> bool {
+        self.difficulty & difficulty.bit() != 0
+    }
+
+    // The sector hint is checked against the real sector by the map
+    fn parse(json: &json::JsonValue) -> Result<(Thing, Option<u32>), String> {
+        let string = |key: &str| {
+            json[key]
+                .as_str()
+                .map(|value| value.to_string())
+                .ok_or(format!("missing '{key}'"))
+        };
+
+        let kind = match json["type"].as_str().ok_or("missing 'type'")? {
+            "player_start" => ThingKind::PlayerStart,
+            "teleport_destination" => ThingKind::TeleportDestination,
+            "enemy" => ThingKind::Enemy(string("enemy")?),
+            "item" => ThingKind::Item {
+                item: string("item")?,
+                respawn: json["respawn"].as_f32(),
+            },
+            "decoration" => ThingKind::Decoration(string("sprite")?),
+            "light" => ThingKind::Light {
+                color: (
+                    json["color"][0].as_f32().unwrap_or(1.0),
+                    json["color"][1].as_f32().unwrap_or(1.0),
+                    json["color"][2].as_f32().unwrap_or(1.0),
+                ),
+                intensity: json["intensity"].as_f32().unwrap_or(1.0),
+                radius: json["radius"].as_f32().ok_or("missing 'radius'")?,
+            },
+            "sound" => ThingKind::SoundEmitter {
+                sound: string("sound")?,
+                radius: json["radius"].as_f32().ok_or("missing 'radius'")?,
+                looping: json["loop"].as_bool().unwrap_or(false),
+            },
+            "trigger" => ThingKind::Trigger {
+                action: string("action")?,
+                radius: json["radius"].as_f32().ok_or("missing 'radius'")?,
+            },
+            other => return Err(format!("unknown type '{other}'")),
+        };
+
+        let position = match (
+            json["position"][0].as_f32(),
+            json["position"][1].as_f32(),
+            json["position"][2].as_f32(),
+        ) {
+            (Some(x), Some(y), Some(z)) => (x, y, z),
+            _ => return Err("missing 'position'".to_string()),
+        };
+
+        // All difficulties if not given
+        let mut difficulty = 0;
+        for name in json["difficulty"].members() {
+            let parsed = name.as_str().and_then(Difficulty::from_name);
+            difficulty |= parsed.ok_or(format!("unknown difficulty {name}"))?.bit();
+        }
+        if json["difficulty"].is_null() {
+            difficulty = Difficulty::ALL.iter().fold(0, |bits, d| bits | d.bit());
+        }
+
+        let thing = Thing {
+            kind,
+            position,
+            angle: json["angle"].as_f32().unwrap_or(0.0),
+            sector: 0,
+            difficulty,
+            properties: json.clone(),
+        };
+        Ok((thing, json["sector"].as_u32()))
+    }
+}
+
+impl Map {
+    // Things outside of every sector are dropped
+    pub fn load_things(&mut self, things: &json::JsonValue) {
+        for (i, json) in things.members().enumerate() {
+            let (mut thing, hint) = match Thing::parse(json) {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    println!("Invalid thing {i} in '{}': {err}", self.path);
+                    continue;
+                }
+            };
+
+            let point = (thing.position.0 as f64, thing.position.2 as f64);
+            let hinted = hint.filter(|sector| {
+                self.sectors
+                    .get(*sector as usize)
+                    .is_some_and(|sector| point_in_polygon(point, &sector.corners))
+            });
+            let Some(sector) =
+                hinted.or_else(|| self.sector_at(thing.position.0, thing.position.2))
+            else {
+                println!("Thing {i} in '{}' is outside of the map", self.path);
+                continue;
+            };
+            if let (Some(hint), None) = (hint, hinted) {
+                println!(
+                    "Thing {i} in '{}' is in sector {sector}, not {hint}",
+                    self.path
+                );
+            }
+
+            thing.sector = sector;
+            self.things.push(thing);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+pub const TICK_RATE: f64 = 35.0; // like Doom
+pub const TICK_TIME: f64 = 1.0 / TICK_RATE;
+
+// Longer frames are cut so the simulation does not spiral after a hitch
+const MAX_FRAME_TIME: f64 = 0.25;
+
+// Accumulates frame time and hands it out in fixed simulation ticks
+pub struct Clock {
+    last_time: f64,
+    accumulator: f64,
+    ticks: u64,
+}
+
+impl Clock {
+    pub fn new(time: f64) -> Self {
+        Clock {
+            last_time: time,
+            accumulator: 0.0,
+            ticks: 0,
+        }
+    }
+
+    // Returns the real time of the last frame
+    pub fn frame(&mut self, time: f64) -> f64 {
+        let delta_time = time - self.last_time;
+        self.last_time = time;
+        self.accumulator += delta_time.min(MAX_FRAME_TIME);
+        delta_time
+    }
+
+    // true as long as there is a tick left to simulate this frame
+    pub fn tick(&mut self) -> bool {
+        if self.accumulator >= TICK_TIME {
+            self.accumulator -= TICK_TIME;
+            self.ticks += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    // How far the renderer is between the last and the next tick (0..1)
+    pub fn alpha(&self) -> f64 {
+        self.accumulator / TICK_TIME
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+}
+
+pub struct FrameLimiter {
+    pub max_fps: Option<f64>,
+    last_time: f64,
+}
+
+impl FrameLimiter {
+    pub fn new(max_fps: Option<f64>, time: f64) -> Self {
+        FrameLimiter {
+            max_fps,
+            last_time: time,
+        }
+    }
+
+    // Sleeps until the next frame is due
+    pub fn wait(&mut self, glfw: &glfw::Glfw) {
+        if let Some(max_fps) = self.max_fps {
+            let target = self.last_time + 1.0 / max_fps;
+            let now = glfw.get_time();
+            if now < target {
+                std::thread::sleep(std::time::Duration::from_secs_f64(target - now));
+            }
+        }
+        self.last_time = glfw.get_time();
+    }
+}
@@ -0,0 +1,229 @@
+use crate::graphics::renderable::mapdata::{texture_path, valid_corners, Corner, Map};
+use crate::graphics::renderable::sector::{CEILING, FLOOR, GATE, WALL};
+use crate::things::ThingKind;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TriggerKind {
+    Walk,  // the player crosses the edge or steps into the sector
+    Use,   // the player uses the edge, like a switch
+    Shoot, // the player shoots the edge
+}
+
+impl TriggerKind {
+    pub fn from_name(name: &str) -> Option<TriggerKind> {
+        match name {
+            "walk" => Some(TriggerKind::Walk),
+            "use" => Some(TriggerKind::Use),
+            "shoot" => Some(TriggerKind::Shoot),
+            _ => None,
+        }
+    }
+}
+
+// What happens to the sectors with the tag
+#[derive(Clone, PartialEq, Debug)]
+pub enum TriggerAction {
+    Door,                 // opens them
+    Lift,                 // starts them
+    Light(f32),           // sets their brightness
+    Texture(u32, String), // replaces one of their textures, FLOOR, CEILING, WALL or GATE
+    Shape(Vec<Corner>),   // gives them new corners, like a wall which breaks down
+    Teleport,             // moves the player into the first one
+    Exit,                 // finishes the level, needs no tag
+}
+
+impl TriggerAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TriggerAction::Door => "door",
+            TriggerAction::Lift => "lift",
+            TriggerAction::Light(_) => "light",
+            TriggerAction::Texture(..) => "texture",
+            TriggerAction::Shape(_) => "shape",
+            TriggerAction::Teleport => "teleport",
+            TriggerAction::Exit => "exit",
+        }
+    }
+}
+
+// A special on an edge of a sector or on the whole sector
+#[derive(Clone, Debug)]
+pub struct TriggerDef {
+    pub edge: Option<usize>, // None for the sector, only walking into it fires those
+    pub kind: TriggerKind,
+    pub repeat: bool, // fires every time instead of once
+    pub action: TriggerAction,
+    pub tag: u32,
+}
+
+impl TriggerDef {
+    pub fn parse(json: &json::JsonValue) -> Option<Self> {
+        let edge = json["edge"].as_usize();
+        let kind = TriggerKind::from_name(json["type"].as_str()?)?;
+        if edge.is_none() && kind != TriggerKind::Walk {
+            return None;
+        }
+        let action = match json["action"].as_str()? {
+            "door" => TriggerAction::Door,
+            "lift" => TriggerAction::Lift,
+            "light" => TriggerAction::Light(json["light"].as_f32()?),
+            "texture" => {
+                let kind = match json["surface"].as_str()? {
+                    "floor" => FLOOR,
+                    "ceiling" => CEILING,
+                    "wall" => WALL,
+                    "gate" => GATE,
+                    _ => return None,
+                };
+                TriggerAction::Texture(kind, json["texture"].as_str()?.to_string())
+            }
+            "shape" => TriggerAction::Shape(
+                json["corners"]
+                    .members()
+                    .map(|corner| Some((corner[0].as_f32()?, corner[1].as_f32()?)))
+                    .collect::<Option<_>>()?,
+            ),
+            "teleport" => TriggerAction::Teleport,
+            "exit" => TriggerAction::Exit,
+            _ => return None,
+        };
+        let tag = match action {
+            TriggerAction::Exit => json["tag"].as_u32().unwrap_or(0),
+            _ => json["tag"].as_u32()?,
+        };
+
+        Some(TriggerDef {
+            edge,
+            kind,
+            repeat: json["repeat"].as_bool().unwrap_or(false),
+            action,
+            tag,
+        })
+    }
+}
+
+impl Map {
+    // Anything a trigger needs has to be in the map, so firing it can not fail
+    pub(crate) fn check_triggers(&self) {
+        for (i, sector) in self.sectors.iter().enumerate() {
+            for trigger in &sector.triggers {
+                let tagged = self
+                    .sectors
+                    .iter()
+                    .filter(|other| other.tag == Some(trigger.tag));
+                match &trigger.action {
+                    TriggerAction::Texture(_, name) => {
+                        texture_path(name).unwrap_or_else(|| {
+                            panic!("Trigger of sector {i} needs the missing texture '{name}'")
+                        });
+                    }
+                    TriggerAction::Shape(corners) => {
+                        for other in tagged {
+                            assert!(
+                                valid_corners(corners, &other.gates),
+                                "Trigger of sector {i} gives invalid corners to tag {}",
+                                trigger.tag
+                            );
+                        }
+                    }
+                    TriggerAction::Teleport => {
+                        let destination = self.things.iter().any(|thing| {
+                            matches!(thing.kind, ThingKind::TeleportDestination)
+                                && self.sectors[thing.sector as usize].tag == Some(trigger.tag)
+                        });
+                        assert!(
+                            destination,
+                            "Trigger of sector {i} has no teleport destination with tag {}",
+                            trigger.tag
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+// Remembers which triggers that only fire once are used up
+pub struct Triggers {
+    used: Vec<Vec<bool>>, // by sector and trigger
+}
+
+impl Triggers {
+    pub fn new(map: &Map) -> Self {
+        let used = map
+            .sectors
+            .iter()
+            .map(|sector| vec![false; sector.triggers.len()])
+            .collect();
+        Triggers { used }
+    }
+
+    // The triggers of the kind on an edge of the sector, or the sector itself with None.
+    // Edges of gates are shared with the neighbour, its triggers on the other side count as well
+    pub fn fire(
+        &mut self,
+        map: &Map,
+        sector: u32,
+        edge: Option<usize>,
+        kind: TriggerKind,
+    ) -> Vec<TriggerDef> {
+        let mut places = vec![(sector, edge)];
+        if let Some(edge) = edge {
+            let gate = map.sectors[sector as usize]
+                .gates
+                .iter()
+                .find(|gate| gate.own as usize == edge);
+            if let Some(gate) = gate {
+                places.push((gate.target_sector, Some(gate.target_gate as usize)));
+            }
+        }
+
+        let mut fired = Vec::new();
+        for (sector, edge) in places {
+            let triggers = &map.sectors[sector as usize].triggers;
+            for (trigger, used) in triggers.iter().zip(&mut self.used[sector as usize]) {
+                if trigger.edge != edge || trigger.kind != kind || *used {
+                    continue;
+                }
+                *used = !trigger.repeat;
+                fired.push(trigger.clone());
+            }
+        }
+        fired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Option<TriggerDef> {
+        TriggerDef::parse(&json::parse(text).unwrap())
+    }
+
+    #[test]
+    fn parse_actions() {
+        let texture = r#"{ "edge": 1, "type": "use", "action": "texture", "surface": "wall",
+                           "texture": "fallback.png", "tag": 2 }"#;
+        let trigger = parse(texture).unwrap();
+        assert_eq!(trigger.kind, TriggerKind::Use);
+        assert_eq!(
+            trigger.action,
+            TriggerAction::Texture(WALL, "fallback.png".to_string())
+        );
+
+        let shape = r#"{ "type": "walk", "action": "shape", "corners": [[0, 0], [1, 0], [1, 1]],
+                         "tag": 2 }"#;
+        let trigger = parse(shape).unwrap();
+        assert_eq!(trigger.edge, None);
+        let corners = vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0)];
+        assert_eq!(trigger.action, TriggerAction::Shape(corners));
+
+        // Only walking into a sector works without an edge, and the surface has to exist
+        assert!(parse(r#"{ "type": "use", "action": "exit" }"#).is_none());
+        let surface = r#"{ "edge": 1, "type": "use", "action": "texture", "surface": "sky",
+                           "texture": "fallback.png", "tag": 2 }"#;
+        assert!(parse(surface).is_none());
+    }
+}
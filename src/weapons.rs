@@ -0,0 +1,327 @@
+use crate::damage::{Damage, DamageType};
+use crate::inventory::Inventory;
+
+pub const CONFIG_PATH: &str = "assets/config/weapons.json";
+
+const LOWER_TIME: f64 = 0.2; // seconds to put a weapon away
+const RAISE_TIME: f64 = 0.2;
+const FLASH_TIME: f64 = 0.1; // seconds the muzzle flash lights up the scene
+const FLASH_LIGHT: f64 = 0.4;
+const BOB_SPEED: f64 = 5.0; // radians per unit walked
+const BOB_HEIGHT: f64 = 0.03; // in screen heights at full speed
+const BOB_FULL: f64 = 2.0; // speed at which the bob is the largest
+const BOB_SMOOTHING: f64 = 8.0; // how fast the bob follows the speed, per second
+
+#[derive(Clone, Debug)]
+pub struct ProjectileDef {
+    pub speed: f64,             // units per second
+    pub radius: f64,            // of the explosion
+    pub sprite: Option<String>, // None is not drawn
+}
+
+#[derive(Clone, Debug)]
+pub struct WeaponDef {
+    pub name: String,
+    pub fire_rate: f64, // shots per second
+    pub damage: Damage, // per pellet
+    pub spread: f64,    // degrees in every direction
+    pub pellets: u32,
+    pub ammo: Option<String>, // None never runs out
+    pub range: f64,
+    pub projectile: Option<ProjectileDef>, // None is hitscan
+    pub sprites: Option<WeaponSprites>,    // None is not drawn
+}
+
+// Frames of the weapon in the hands of the player, see Sprite
+#[derive(Clone, Debug)]
+pub struct WeaponSprites {
+    pub ready: String,
+    pub fire: Vec<String>, // spread over the time between two shots
+    pub flash: Option<String>,
+}
+
+impl WeaponSprites {
+    fn parse(json: &json::JsonValue) -> Option<Self> {
+        Some(WeaponSprites {
+            ready: json["ready"].as_str()?.to_string(),
+            fire: json["fire"]
+                .members()
+                .filter_map(|frame| frame.as_str().map(|frame| frame.to_string()))
+                .collect(),
+            flash: json["flash"].as_str().map(|flash| flash.to_string()),
+        })
+    }
+}
+
+// How the current weapon gets drawn
+#[derive(Clone, Debug)]
+pub struct WeaponView {
+    pub frame: String,
+    pub flash: Option<String>, // drawn over the frame
+    pub offset: (f64, f64),    // x, y from the bottom center, in screen heights
+    pub light: f64,            // added to the light of the scene
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Switch {
+    Ready,
+    Lowering(usize), // to the slot
+    Raising,
+}
+
+impl ProjectileDef {
+    pub fn parse(json: &json::JsonValue) -> Option<Self> {
+        Some(ProjectileDef {
+            speed: json["speed"].as_f64().filter(|speed| *speed > 0.0)?,
+            radius: json["radius"].as_f64().filter(|radius| *radius >= 0.0)?,
+            sprite: json["sprite"].as_str().map(str::to_string),
+        })
+    }
+}
+
+impl WeaponDef {
+    fn parse(json: &json::JsonValue) -> Option<Self> {
+        let projectile = if json["projectile"].is_null() {
+            None
+        } else {
+            Some(ProjectileDef::parse(&json["projectile"])?)
+        };
+
+        Some(WeaponDef {
+            name: json["name"].as_str()?.to_string(),
+            fire_rate: json["fireRate"].as_f64().filter(|rate| *rate > 0.0)?,
+            damage: Damage::new(
+                json["damage"].as_i32()?,
+                DamageType::from_name(json["damageType"].as_str()?)?,
+            ),
+            spread: json["spread"].as_f64().unwrap_or(0.0),
+            pellets: json["pellets"].as_u32().unwrap_or(1),
+            ammo: json["ammo"].as_str().map(|ammo| ammo.to_string()),
+            range: json["range"].as_f64().filter(|range| *range >= 0.0)?,
+            projectile,
+            sprites: if json["sprites"].is_null() {
+                None
+            } else {
+                Some(WeaponSprites::parse(&json["sprites"])?)
+            },
+        })
+    }
+}
+
+// The weapons the player carries, selected by slot (position in the weapon list)
+pub struct Weapons {
+    defs: Vec<WeaponDef>,
+    current: usize,
+    cooldown: f64, // seconds until the next shot
+    switch: Switch,
+    switch_time: f64,       // seconds into lowering or raising
+    shot_time: Option<f64>, // seconds since the last shot, until the fire frames are done
+    bob: f64,               // phase
+    bob_amount: f64,        // 0 standing still to 1 at full speed
+}
+
+impl Weapons {
+    pub fn new() -> Self {
+        Weapons {
+            defs: Vec::new(),
+            current: 0,
+            cooldown: 0.0,
+            switch: Switch::Ready,
+            switch_time: 0.0,
+            shot_time: None,
+            bob: 0.0,
+            bob_amount: 0.0,
+        }
+    }
+
+    pub fn load(path: &str) -> Self {
+        let input = std::fs::read_to_string(path).expect("Could not find weapon list");
+        let config = json::parse(&input).expect("Could not parse weapon list");
+
+        let mut weapons = Weapons::new();
+        for weapon in config["weapons"].members() {
+            match WeaponDef::parse(weapon) {
+                Some(def) => weapons.defs.push(def),
+                None => println!("Invalid weapon {weapon} in '{path}'"),
+            }
+        }
+        weapons
+    }
+
+    // The slot being switched to or the current one
+    fn target(&self) -> usize {
+        match self.switch {
+            Switch::Lowering(slot) => slot,
+            _ => self.current,
+        }
+    }
+
+    // The current weapon gets lowered first, the new one raised after
+    pub fn select(&mut self, slot: usize) -> bool {
+        if slot >= self.defs.len() || slot == self.target() {
+            return false;
+        }
+
+        self.switch_time = match self.switch {
+            Switch::Ready => 0.0,
+            Switch::Lowering(_) => self.switch_time,
+            // Goes back down from where it is
+            Switch::Raising => (1.0 - self.switch_time / RAISE_TIME) * LOWER_TIME,
+        };
+        self.switch = Switch::Lowering(slot);
+        true
+    }
+
+    // Steps through the slots, wrapping around at the ends
+    pub fn cycle(&mut self, step: isize) {
+        if self.defs.is_empty() {
+            return;
+        }
+        let len = self.defs.len() as isize;
+        self.select((self.target() as isize + step).rem_euclid(len) as usize);
+    }
+
+    // Stops below zero by less than a tick, the next shot makes up for having been late.
+    // Speed is how fast the player moves, for the bob
+    pub fn update(&mut self, delta_time: f64, speed: f64) {
+        if self.cooldown > 0.0 {
+            self.cooldown -= delta_time;
+        }
+
+        self.switch_time += delta_time;
+        match self.switch {
+            Switch::Ready => {}
+            Switch::Lowering(slot) => {
+                if self.switch_time >= LOWER_TIME {
+                    self.current = slot;
+                    self.switch = Switch::Raising;
+                    self.switch_time = 0.0;
+                    self.shot_time = None;
+                }
+            }
+            Switch::Raising => {
+                if self.switch_time >= RAISE_TIME {
+                    self.switch = Switch::Ready;
+                }
+            }
+        }
+
+        if let Some(time) = &mut self.shot_time {
+            *time += delta_time;
+            let fire_rate = self.defs.get(self.current).map_or(1.0, |def| def.fire_rate);
+            if *time >= 1.0 / fire_rate {
+                self.shot_time = None;
+            }
+        }
+
+        let amount = (speed / BOB_FULL).min(1.0);
+        self.bob_amount += (amount - self.bob_amount) * (BOB_SMOOTHING * delta_time).min(1.0);
+        self.bob += speed * delta_time * BOB_SPEED;
+    }
+
+    // Takes the ammo for one shot of the current weapon if it is ready
+    pub fn fire(&mut self, inventory: &mut Inventory) -> Option<WeaponDef> {
+        let def = self.defs.get(self.current)?;
+        if self.cooldown > 0.0 || self.switch != Switch::Ready {
+            return None;
+        }
+
+        if let Some(kind) = &def.ammo {
+            if !inventory.take_ammo(kind, 1) {
+                return None;
+            }
+        }
+
+        self.cooldown += 1.0 / def.fire_rate;
+        self.shot_time = Some(0.0);
+        Some(def.clone())
+    }
+
+    // None if the weapon has no sprites
+    pub fn view(&self) -> Option<WeaponView> {
+        let def = self.defs.get(self.current)?;
+        let sprites = def.sprites.as_ref()?;
+
+        let mut frame = &sprites.ready;
+        let mut flash = None;
+        let mut light = 0.0;
+        if let Some(time) = self.shot_time {
+            if !sprites.fire.is_empty() {
+                let index = (time * def.fire_rate * sprites.fire.len() as f64) as usize;
+                frame = &sprites.fire[index.min(sprites.fire.len() - 1)];
+            }
+            if time < FLASH_TIME {
+                flash = sprites.flash.clone();
+                light = FLASH_LIGHT * (1.0 - time / FLASH_TIME);
+            }
+        }
+
+        // Lowered all the way is one screen height down
+        let lowered = match self.switch {
+            Switch::Ready => 0.0,
+            Switch::Lowering(_) => (self.switch_time / LOWER_TIME).min(1.0),
+            Switch::Raising => 1.0 - (self.switch_time / RAISE_TIME).min(1.0),
+        };
+        let bob = BOB_HEIGHT * self.bob_amount;
+        Some(WeaponView {
+            frame: frame.clone(),
+            flash,
+            offset: (
+                self.bob.cos() * bob * 2.0,
+                -self.bob.sin().abs() * bob - lowered,
+            ),
+            light,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weapon(fields: &str) -> Option<WeaponDef> {
+        WeaponDef::parse(
+            &json::parse(&format!(
+                r#"{{ "name": "test", "damage": 1, "damageType": "hitscan", {fields} }}"#
+            ))
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn parse_weapons() {
+        let def = weapon(r#""fireRate": 2.0, "range": 10.0"#).unwrap();
+        assert_eq!(def.fire_rate, 2.0);
+        assert_eq!(def.pellets, 1);
+        assert!(def.projectile.is_none());
+
+        let def = weapon(
+            r#""fireRate": 1.0, "range": 10.0,
+               "projectile": { "speed": 5.0, "radius": 0.0 }"#,
+        )
+        .unwrap();
+        assert_eq!(def.projectile.unwrap().speed, 5.0);
+
+        assert!(weapon(r#""range": 10.0"#).is_none());
+        assert!(weapon(r#""fireRate": 0.0, "range": 10.0"#).is_none());
+        assert!(weapon(r#""fireRate": 1.0, "range": -1.0"#).is_none());
+        assert!(weapon(
+            r#""fireRate": 1.0, "range": 10.0, "projectile": { "speed": 0.0, "radius": 1.0 }"#
+        )
+        .is_none());
+        assert!(weapon(
+            r#""fireRate": 1.0, "range": 10.0, "projectile": { "speed": 5.0, "radius": -1.0 }"#
+        )
+        .is_none());
+    }
+
+    // Every weapon in the config has to make it through
+    #[test]
+    fn load_config() {
+        let config = json::parse(&std::fs::read_to_string(CONFIG_PATH).unwrap()).unwrap();
+        assert_eq!(
+            Weapons::load(CONFIG_PATH).defs.len(),
+            config["weapons"].members().count()
+        );
+    }
+}
@@ -0,0 +1,891 @@
+use crate::actors::{crossed_edge, Actor, ActorId, ActorKind, Actors};
+use crate::damage::{Damage, DamageType, Vitals};
+use crate::doors::{Activation, DoorState, Doors};
+use crate::enemies::{
+    load_enemies, AttackDef, AttackKind, Enemy, EnemyDef, EnemyState, Target,
+    CONFIG_PATH as ENEMIES_PATH,
+};
+use crate::graphics::camera::front;
+use crate::graphics::renderable::mapdata::Map;
+use crate::graphics::renderable::raycast::RayHit;
+use crate::graphics::renderable::sprites::Sprite;
+use crate::graphics::renderable::RenderableShader;
+use crate::input::{Action, TickCommand};
+use crate::inventory::{load_items, Inventory, ItemDef, Powerup, ITEMS_PATH};
+use crate::lifts::{LiftState, LiftTrigger, Lifts};
+use crate::navigation::Pathfinder;
+use crate::noise;
+use crate::player::Player;
+use crate::random::Random;
+use crate::things::{Difficulty, Thing, ThingKind};
+use crate::timing::TICK_TIME;
+use crate::triggers::{TriggerAction, TriggerDef, TriggerKind, Triggers};
+use crate::weapons::{WeaponDef, Weapons, CONFIG_PATH as WEAPONS_PATH};
+use nalgebra_glm as ng;
+use std::collections::HashMap;
+
+const CHECKPOINT_RADIUS: f64 = 0.5;
+const PICKUP_RADIUS: f64 = 0.4;
+const QUAD_DAMAGE: i32 = 4;
+const DECORATION_RADIUS: f64 = 0.25;
+const DECORATION_HEIGHT: f32 = 0.5;
+const USE_RANGE: f64 = 0.6; // how far away gates can be used
+
+const WEAPON_SLOTS: [Action; 5] = [
+    Action::Weapon1,
+    Action::Weapon2,
+    Action::Weapon3,
+    Action::Weapon4,
+    Action::Weapon5,
+];
+
+// Everything in map coordinates
+pub struct Projectile {
+    pub position: ng::DVec3,
+    pub velocity: ng::DVec3,
+    pub damage: Damage,
+    pub radius: f64,    // of the explosion
+    pub range: f64,     // distance left before it fizzles out
+    pub owner: ActorId, // it does not hit its owner
+    pub sprite: Option<String>,
+}
+
+// An item lying in the map, map coordinates
+pub struct Pickup {
+    pub item: String,
+    pub position: ng::DVec3,
+    pub sector: u32,
+    pub respawn: Option<f64>, // seconds after being picked up
+    pub hidden: f64,          // seconds until it is back, 0 if it is there
+}
+
+// Things which happened during the last tick, for effects and sounds
+#[derive(Clone, Debug)]
+pub enum Event {
+    Shot(String), // weapon name
+    Impact(RayHit),
+    Hit(ActorId, i32), // health lost
+    Killed(ActorId),
+    Alert(ActorId), // an enemy noticed the player
+    Attack(ActorId),
+    Explosion(ng::DVec3, f64),   // position, radius
+    Pickup(String),              // message
+    Door(u32, DoorState),        // sector, new state
+    Locked(String),              // key needed
+    Lift(u32, LiftState),        // sector, new state
+    Trigger(TriggerAction, u32), // tag
+}
+
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Event::Shot(weapon) => write!(f, "shot {weapon}"),
+            Event::Impact(hit) => write!(
+                f,
+                "impact {:?} at {:.4} {:.4} {:.4} | sector {}",
+                hit.kind, hit.point.x, hit.point.y, hit.point.z, hit.sector
+            ),
+            Event::Hit(actor, lost) => write!(f, "hit actor {actor} | health lost {lost}"),
+            Event::Killed(actor) => write!(f, "killed actor {actor}"),
+            Event::Alert(actor) => write!(f, "alert actor {actor}"),
+            Event::Attack(actor) => write!(f, "attack actor {actor}"),
+            Event::Explosion(pos, radius) => write!(
+                f,
+                "explosion at {:.4} {:.4} {:.4} | radius {radius}",
+                pos.x, pos.y, pos.z
+            ),
+            Event::Pickup(message) => write!(f, "pickup '{message}'"),
+            Event::Door(sector, state) => write!(f, "door {sector} {}", state.name()),
+            Event::Locked(key) => write!(f, "locked, needs the {key} key"),
+            Event::Lift(sector, state) => write!(f, "lift {sector} {}", state.name()),
+            Event::Trigger(action, tag) => write!(f, "trigger {} tag {tag}", action.name()),
+        }
+    }
+}
+
+// Everything that is simulated, independent of a window or OpenGL
+pub struct World {
+    pub map: Map,
+    pub player: Player,
+    pub actors: Actors,
+    pub enemies: Vec<Enemy>,
+    pub projectiles: Vec<Projectile>,
+    pub pickups: Vec<Pickup>,
+    doors: Doors,
+    lifts: Lifts,
+    triggers: Triggers,
+    items: HashMap<String, ItemDef>,
+    enemy_defs: HashMap<String, EnemyDef>,
+    paths: Pathfinder,
+    events: Vec<Event>,
+    random: Random,
+    tick: u64,
+    finished: bool, // the level got completed
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WorldState {
+    pub tick: u64,
+    pub position: (f64, f64, f64),
+    pub sector: u32,
+    pub health: i32,
+    pub armor: i32,
+}
+
+impl std::fmt::Display for WorldState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "tick {} | position {:.4} {:.4} {:.4} | sector {} | health {} | armor {}",
+            self.tick,
+            self.position.0,
+            self.position.1,
+            self.position.2,
+            self.sector,
+            self.health,
+            self.armor
+        )
+    }
+}
+
+impl World {
+    // Map file relative to assets/maps, the same seed always plays out the same
+    pub fn load(map: &str, seed: u64, difficulty: Difficulty) -> Self {
+        let mut map_data = Map::new();
+        map_data.load(map);
+        World::new(
+            map_data,
+            Weapons::load(WEAPONS_PATH),
+            Inventory::load(WEAPONS_PATH),
+            load_items(ITEMS_PATH),
+            load_enemies(ENEMIES_PATH),
+            seed,
+            difficulty,
+        )
+    }
+
+    pub fn new(
+        map: Map,
+        weapons: Weapons,
+        inventory: Inventory,
+        items: HashMap<String, ItemDef>,
+        enemy_defs: HashMap<String, EnemyDef>,
+        seed: u64,
+        difficulty: Difficulty,
+    ) -> Self {
+        let mut actors = Actors::default();
+        let player = Player::new(
+            map.spawn,
+            map.spawn_angle,
+            &map,
+            weapons,
+            inventory,
+            &mut actors,
+        );
+        let doors = Doors::new(&map);
+        let lifts = Lifts::new(&map);
+        let triggers = Triggers::new(&map);
+
+        let mut enemies = Vec::new();
+        let mut pickups = Vec::new();
+        for thing in map
+            .things
+            .iter()
+            .filter(|thing| thing.appears_on(difficulty))
+        {
+            if let ThingKind::Decoration(sprite) = &thing.kind {
+                actors.spawn(decoration(thing, sprite));
+                continue;
+            }
+            if let ThingKind::Enemy(kind) = &thing.kind {
+                match enemy_defs.get(kind) {
+                    Some(def) => {
+                        let actor = actors.spawn(monster(thing, def));
+                        let mut enemy = Enemy::new(kind, actor);
+                        enemy.deaf = thing.properties["ambush"].as_bool().unwrap_or(false);
+                        enemies.push(enemy);
+                    }
+                    None => println!("Unknown enemy '{kind}' in '{}'", map.path),
+                }
+                continue;
+            }
+            let ThingKind::Item { item, respawn } = &thing.kind else {
+                continue;
+            };
+            if !items.contains_key(item) {
+                println!("Unknown item '{item}' in '{}'", map.path);
+                continue;
+            }
+            pickups.push(Pickup {
+                item: item.clone(),
+                position: ng::DVec3::new(
+                    thing.position.0 as f64,
+                    thing.position.1 as f64,
+                    thing.position.2 as f64,
+                ),
+                sector: thing.sector,
+                respawn: respawn.map(|time| time as f64),
+                hidden: 0.0,
+            });
+        }
+
+        World {
+            map,
+            player,
+            actors,
+            enemies,
+            projectiles: Vec::new(),
+            pickups,
+            doors,
+            lifts,
+            triggers,
+            items,
+            enemy_defs,
+            paths: Pathfinder::default(),
+            events: Vec::new(),
+            random: Random::new(seed),
+            tick: 0,
+            finished: false,
+        }
+    }
+
+    pub fn tick(&mut self, cmd: &TickCommand) {
+        self.events.clear();
+
+        if self.player.dead() {
+            if cmd.pressed(Action::Respawn) && self.player.death_finished() {
+                self.player.respawn(&self.map, &mut self.actors);
+            }
+        } else {
+            self.handle_actions(cmd);
+        }
+        // Before the player so it stands on where the floors are now
+        self.update_doors();
+        self.update_lifts();
+
+        self.player
+            .update(cmd, TICK_TIME, &self.map, &mut self.actors);
+        if let Some((sector, edge)) = self.player.crossed() {
+            if !self.player.dead() {
+                let entered = self.player.sector(&self.actors);
+                self.trigger_lift(entered, LiftTrigger::Walk);
+                self.fire_triggers(sector, Some(edge), TriggerKind::Walk);
+                self.fire_triggers(entered, None, TriggerKind::Walk);
+            }
+        }
+        self.player.weapons.update(TICK_TIME, self.player.speed());
+        if cmd.held(Action::Fire) && !self.player.dead() {
+            if let Some(weapon) = self.player.weapons.fire(&mut self.player.inventory) {
+                self.fire(&weapon);
+                self.make_noise(self.player.sector(&self.actors));
+            }
+        }
+        self.actors.update(TICK_TIME, &self.map);
+        self.update_enemies();
+        self.update_projectiles();
+
+        self.map.update(TICK_TIME as f32);
+        self.check_checkpoints();
+        self.update_pickups();
+
+        self.tick += 1;
+    }
+
+    fn handle_actions(&mut self, cmd: &TickCommand) {
+        if cmd.pressed(Action::ToggleSpectator) {
+            self.player.toggle_spectator(&self.map, &mut self.actors);
+        }
+        if cmd.pressed(Action::TeleportSpawn) {
+            let spawn = self.map.spawn;
+            self.player
+                .teleport(spawn.0 as f64, spawn.2 as f64, &self.map, &mut self.actors);
+        }
+        if cmd.pressed(Action::DebugDamage) {
+            self.player
+                .damage(Damage::new(1, DamageType::Hitscan), &mut self.actors);
+        }
+        if cmd.pressed(Action::DebugHeal) {
+            self.player.heal(1, true, &mut self.actors);
+        }
+        if cmd.pressed(Action::DebugArmor) {
+            self.player.add_armor(1, 0.5, &mut self.actors);
+        }
+        if cmd.pressed(Action::DebugFinish) {
+            self.finished = true;
+        }
+        if cmd.pressed(Action::Use) {
+            self.use_gate();
+        }
+
+        for (slot, action) in WEAPON_SLOTS.iter().enumerate() {
+            if cmd.pressed(*action) {
+                self.player.weapons.select(slot);
+            }
+        }
+        if cmd.pressed(Action::NextWeapon) {
+            self.player.weapons.cycle(1);
+        }
+        if cmd.pressed(Action::PrevWeapon) {
+            self.player.weapons.cycle(-1);
+        }
+    }
+
+    // Every pellet goes into a random direction within the spread
+    fn fire(&mut self, weapon: &WeaponDef) {
+        self.events.push(Event::Shot(weapon.name.clone()));
+
+        let mut damage = weapon.damage;
+        if self.player.inventory.has_powerup(Powerup::QuadDamage) {
+            damage.amount *= QUAD_DAMAGE;
+        }
+
+        let origin = self.player.eye();
+        let (yaw, pitch) = self.player.aim();
+        for _ in 0..weapon.pellets {
+            let direction = front(
+                yaw + self.random.range(-weapon.spread, weapon.spread),
+                pitch + self.random.range(-weapon.spread, weapon.spread),
+            );
+            let direction = ng::DVec3::new(direction.x, direction.y, -direction.z);
+
+            match &weapon.projectile {
+                Some(projectile) => self.projectiles.push(Projectile {
+                    position: origin,
+                    velocity: direction * projectile.speed,
+                    damage,
+                    radius: projectile.radius,
+                    range: weapon.range,
+                    owner: self.player.id(),
+                    sprite: projectile.sprite.clone(),
+                }),
+                None => {
+                    let hit = self.map.raycast(origin, direction, weapon.range);
+                    let range = hit.map_or(weapon.range, |hit| hit.distance);
+                    let shooter = Some(self.player.id());
+                    match self.actors.raycast(origin, direction, range, shooter) {
+                        Some((actor, _)) => self.damage_actor(actor, damage),
+                        None => {
+                            if let Some(hit) = hit {
+                                if let Some(edge) = hit.edge {
+                                    let edge = Some(edge as usize);
+                                    self.fire_triggers(hit.sector, edge, TriggerKind::Shoot);
+                                }
+                                self.events.push(Event::Impact(hit));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Touching an item picks it up if the player can make use of it
+    fn update_pickups(&mut self) {
+        if self.player.spectator() {
+            return;
+        }
+        let eye = self.player.eye();
+        let mut i = 0;
+        while i < self.pickups.len() {
+            let pickup = &mut self.pickups[i];
+            if pickup.hidden > 0.0 {
+                pickup.hidden = (pickup.hidden - TICK_TIME).max(0.0);
+                i += 1;
+                continue;
+            }
+
+            let distance =
+                crate::geometry::distance((eye.x, eye.z), (pickup.position.x, pickup.position.z));
+            let item = &self.items[&pickup.item];
+            if distance > PICKUP_RADIUS || !self.player.pick_up(item, &mut self.actors) {
+                i += 1;
+                continue;
+            }
+
+            self.events.push(Event::Pickup(item.message.clone()));
+            match pickup.respawn {
+                Some(time) => {
+                    pickup.hidden = time;
+                    i += 1;
+                }
+                None => {
+                    self.pickups.remove(i);
+                }
+            }
+        }
+    }
+
+    fn update_projectiles(&mut self) {
+        let mut explosions = Vec::new();
+        let mut shot_edges = Vec::new();
+        let player = self.player.id();
+        self.projectiles.retain_mut(|projectile| {
+            let step = projectile.velocity * TICK_TIME;
+            let length = ng::length(&step);
+            let direction = ng::normalize(&step);
+            let hit = self.map.raycast(projectile.position, step, length);
+            let range = hit.map_or(length, |hit| hit.distance);
+            let owner = Some(projectile.owner);
+            let actor_hit = self
+                .actors
+                .raycast(projectile.position, direction, range, owner);
+            if let Some((_, distance)) = actor_hit {
+                explosions.push((
+                    projectile.position + direction * distance,
+                    projectile.damage,
+                    projectile.radius,
+                ));
+                return false;
+            }
+            if let Some(hit) = hit {
+                // Only the player shoots triggers, like with hitscan weapons
+                if let Some(edge) = hit.edge.filter(|_| projectile.owner == player) {
+                    shot_edges.push((hit.sector, edge as usize));
+                }
+                // Explode a bit in front of the surface
+                explosions.push((
+                    hit.point + hit.normal * 0.01,
+                    projectile.damage,
+                    projectile.radius,
+                ));
+                return false;
+            }
+
+            projectile.position += step;
+            projectile.range -= length;
+            projectile.range > 0.0
+        });
+
+        for (sector, edge) in shot_edges {
+            self.fire_triggers(sector, Some(edge), TriggerKind::Shoot);
+        }
+        for (position, damage, radius) in explosions {
+            self.explode(position, damage, radius);
+        }
+    }
+
+    // Full damage at the center, none at the radius
+    fn explode(&mut self, position: ng::DVec3, damage: Damage, radius: f64) {
+        self.events.push(Event::Explosion(position, radius));
+
+        let falloff = |distance: f64| {
+            let amount = (damage.amount as f64 * (1.0 - distance / radius)).round() as i32;
+            Damage::new(amount, damage.kind)
+        };
+
+        // Walls shield everything further away than the sectors next to the explosion
+        let Some(sector) = self.map.sector_at(position.x as f32, position.z as f32) else {
+            return;
+        };
+        let mut sectors = vec![sector];
+        sectors.extend(
+            self.map.sectors[sector as usize]
+                .gates
+                .iter()
+                .map(|gate| gate.target_sector),
+        );
+        let mut hit = Vec::new();
+        for sector in sectors {
+            for (id, actor) in self.actors.in_sector(sector) {
+                let distance = ng::distance(&actor.center(), &position);
+                if distance < radius && !hit.iter().any(|(other, _)| *other == id) {
+                    hit.push((id, distance));
+                }
+            }
+        }
+        for (id, distance) in hit {
+            self.damage_actor(id, falloff(distance));
+        }
+    }
+
+    // The player takes damage its own way
+    fn damage_actor(&mut self, id: ActorId, damage: Damage) {
+        if id == self.player.id() {
+            self.player.damage(damage, &mut self.actors);
+            return;
+        }
+        let Some(actor) = self.actors.get_mut(id) else {
+            return;
+        };
+        let lost = actor.damage(damage);
+        if lost > 0 {
+            self.events.push(Event::Hit(id, lost));
+            if actor.dead() {
+                self.events.push(Event::Killed(id));
+            }
+
+            if let Some(enemy) = self.enemies.iter_mut().find(|enemy| enemy.actor == id) {
+                let roll = self.random.next_f64();
+                enemy.hurt(&self.enemy_defs[&enemy.kind], actor.dead(), roll);
+            }
+        }
+
+        // Destroyed decorations are gone
+        if actor.dead() && actor.kind == ActorKind::Decoration {
+            self.actors.remove(id);
+        }
+    }
+
+    // Uses a switch on the edge in front of the player, or else the door or lift behind the gate
+    // in front of the player, or the one the player is standing in
+    fn use_gate(&mut self) {
+        let eye = self.player.eye();
+        let (yaw, _) = self.player.aim();
+        let direction = front(yaw, 0.0);
+        let from = (eye.x, eye.z);
+        let to = (
+            from.0 + direction.x * USE_RANGE,
+            from.1 - direction.z * USE_RANGE,
+        );
+
+        let sector = self.player.sector(&self.actors);
+        let edge = crossed_edge(&self.map, sector, from, to);
+        if edge.is_some() && self.fire_triggers(sector, edge, TriggerKind::Use) {
+            return;
+        }
+
+        let current = &self.map.sectors[sector as usize];
+        let target = edge
+            .and_then(|edge| current.gates.iter().find(|gate| gate.own as usize == edge))
+            .map(|gate| gate.target_sector);
+        let door = target
+            .filter(|target| self.map.sectors[*target as usize].door.is_some())
+            .or(current.door.as_ref().map(|_| sector));
+        let Some(door) = door else {
+            // Lifts are used the same way
+            if !self.trigger_lift(target.unwrap_or(sector), LiftTrigger::Use) {
+                self.trigger_lift(sector, LiftTrigger::Use);
+            }
+            return;
+        };
+
+        match self.doors.activate(&self.map, door, &self.player.inventory) {
+            Some(Activation::Moved) => {
+                if let Some(state) = self.doors.state(door) {
+                    self.events.push(Event::Door(door, state));
+                }
+            }
+            Some(Activation::Locked(key)) => self.events.push(Event::Locked(key)),
+            Some(Activation::Busy) | None => {}
+        }
+    }
+
+    // Closing doors open again when something is in the way
+    fn update_doors(&mut self) {
+        let occupants: Vec<(u32, f32)> = self
+            .actors
+            .iter()
+            .filter(|(_, actor)| !actor.dead())
+            .map(|(_, actor)| (actor.sector, actor.height))
+            .collect();
+
+        let changed = self.doors.update(&mut self.map, &occupants, TICK_TIME);
+        if !changed.is_empty() {
+            // Gates got opened or closed
+            self.paths.clear();
+        }
+        for (sector, state) in changed {
+            self.events.push(Event::Door(sector, state));
+        }
+    }
+
+    // Returns true if any trigger fired
+    fn fire_triggers(&mut self, sector: u32, edge: Option<usize>, kind: TriggerKind) -> bool {
+        let fired = self.triggers.fire(&self.map, sector, edge, kind);
+        for trigger in &fired {
+            self.apply_trigger(trigger);
+        }
+        !fired.is_empty()
+    }
+
+    // Acts on all sectors with the tag of the trigger
+    fn apply_trigger(&mut self, trigger: &TriggerDef) {
+        self.events
+            .push(Event::Trigger(trigger.action.clone(), trigger.tag));
+
+        let tagged: Vec<u32> = (0..self.map.sectors.len() as u32)
+            .filter(|sector| self.map.sectors[*sector as usize].tag == Some(trigger.tag))
+            .collect();
+        match &trigger.action {
+            TriggerAction::Door => {
+                for sector in tagged {
+                    if self.doors.open(sector) {
+                        self.events.push(Event::Door(sector, DoorState::Opening));
+                    }
+                }
+            }
+            TriggerAction::Lift => {
+                for sector in tagged {
+                    if self.lifts.start(sector) {
+                        self.events.push(Event::Lift(sector, LiftState::Leaving));
+                    }
+                }
+            }
+            TriggerAction::Light(light) => {
+                for sector in tagged {
+                    self.map.sectors[sector as usize].light = *light;
+                }
+            }
+            TriggerAction::Texture(kind, name) => {
+                for sector in tagged {
+                    self.map.set_texture(sector, *kind, name);
+                }
+            }
+            TriggerAction::Shape(corners) => {
+                for sector in tagged {
+                    self.map.set_corners(sector, corners.clone());
+                }
+                self.paths.clear();
+            }
+            TriggerAction::Teleport => {
+                let destination = self.map.things.iter().find(|thing| {
+                    matches!(thing.kind, ThingKind::TeleportDestination)
+                        && tagged.contains(&thing.sector)
+                });
+                let destination = destination.expect("Teleport destinations are checked on load");
+                let (x, _, z) = destination.position;
+                let angle = destination.angle;
+                if self
+                    .player
+                    .teleport(x as f64, z as f64, &self.map, &mut self.actors)
+                {
+                    self.player.face(angle);
+                }
+            }
+            TriggerAction::Exit => self.finished = true,
+        }
+    }
+
+    fn trigger_lift(&mut self, sector: u32, trigger: LiftTrigger) -> bool {
+        if !self.lifts.activate(&self.map, sector, trigger) {
+            return false;
+        }
+        self.events.push(Event::Lift(sector, LiftState::Leaving));
+        true
+    }
+
+    // Crushers hurt everything in the way
+    fn update_lifts(&mut self) {
+        let mut occupants: Vec<(u32, f32)> = Vec::new();
+        let mut ids = Vec::new();
+        for (id, actor) in self.actors.iter().filter(|(_, actor)| !actor.dead()) {
+            occupants.push((actor.sector, actor.height));
+            ids.push(id);
+        }
+
+        let changes = self.lifts.update(&mut self.map, &occupants, TICK_TIME);
+        if !changes.states.is_empty() {
+            self.paths.clear();
+        }
+        for (sector, state) in changes.states {
+            self.events.push(Event::Lift(sector, state));
+        }
+        for (occupant, damage) in changes.crushed {
+            self.damage_actor(ids[occupant], damage);
+        }
+    }
+
+    // Wakes up the enemies which can hear it
+    fn make_noise(&mut self, sector: u32) {
+        let heard = noise::propagate(&self.map, sector);
+        for enemy in &mut self.enemies {
+            let Some(actor) = self.actors.get(enemy.actor) else {
+                continue;
+            };
+            if heard[actor.sector as usize] && enemy.hear() {
+                self.events.push(Event::Alert(enemy.actor));
+            }
+        }
+    }
+
+    fn update_enemies(&mut self) {
+        let target = Target {
+            eye: self.player.eye(),
+            alive: !self.player.dead(),
+        };
+
+        let mut attacks = Vec::new();
+        for enemy in &mut self.enemies {
+            let Some(actor) = self.actors.get_mut(enemy.actor) else {
+                continue;
+            };
+            let def = &self.enemy_defs[&enemy.kind];
+            let idle = enemy.state() == EnemyState::Idle;
+            if enemy.update(def, actor, &target, &self.map, &mut self.paths, TICK_TIME) {
+                attacks.push((enemy.actor, def.attack.clone()));
+            }
+            if idle && enemy.state() == EnemyState::See {
+                self.events.push(Event::Alert(enemy.actor));
+            }
+        }
+
+        for (id, attack) in attacks {
+            self.enemy_attack(id, &attack);
+        }
+    }
+
+    // Enemies aim at the player, shots hit whatever is in the way
+    fn enemy_attack(&mut self, id: ActorId, attack: &AttackDef) {
+        let Some(actor) = self.actors.get_mut(id) else {
+            return;
+        };
+        self.events.push(Event::Attack(id));
+
+        let origin = actor.eye();
+        let target = self.player.eye();
+        let direction = ng::normalize(&(target - origin));
+        match &attack.kind {
+            AttackKind::Melee => {
+                let distance = ng::distance(&origin, &target);
+                if distance <= attack.range + self.player.body(&self.actors).radius {
+                    self.damage_actor(self.player.id(), attack.damage);
+                }
+            }
+            AttackKind::Hitscan { spread } => {
+                let spread = spread.to_radians().tan();
+                let offset = ng::DVec3::new(
+                    self.random.range(-spread, spread),
+                    self.random.range(-spread, spread),
+                    self.random.range(-spread, spread),
+                );
+                let direction = ng::normalize(&(direction + offset));
+
+                let hit = self.map.raycast(origin, direction, attack.range);
+                let range = hit.map_or(attack.range, |hit| hit.distance);
+                match self.actors.raycast(origin, direction, range, Some(id)) {
+                    Some((actor, _)) => self.damage_actor(actor, attack.damage),
+                    None => {
+                        if let Some(hit) = hit {
+                            self.events.push(Event::Impact(hit));
+                        }
+                    }
+                }
+            }
+            AttackKind::Projectile(projectile) => self.projectiles.push(Projectile {
+                position: origin,
+                velocity: direction * projectile.speed,
+                damage: attack.damage,
+                radius: projectile.radius,
+                range: attack.range,
+                owner: id,
+                sprite: projectile.sprite.clone(),
+            }),
+        }
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    fn check_checkpoints(&mut self) {
+        if self.player.dead() {
+            return;
+        }
+
+        let pos = self.player.position();
+        for &checkpoint in &self.map.checkpoints {
+            let distance = crate::geometry::distance(
+                (pos.x, -pos.z),
+                (checkpoint.0 as f64, checkpoint.2 as f64),
+            );
+            if distance <= CHECKPOINT_RADIUS && self.player.set_checkpoint(checkpoint) {
+                println!("Checkpoint reached");
+            }
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn state(&self) -> WorldState {
+        let pos = self.player.position();
+        WorldState {
+            tick: self.tick,
+            position: (pos.x, pos.y, pos.z),
+            sector: self.player.sector(&self.actors),
+            health: self.player.health(&self.actors),
+            armor: self.player.armor(&self.actors),
+        }
+    }
+
+    // Everything with a sprite, world coordinates
+    pub fn sprites(&self) -> Vec<Sprite> {
+        let sprite = |frame: &Option<String>, position: ng::DVec3, angle: f64, sector: u32| {
+            Some(Sprite {
+                frame: frame.clone()?,
+                position: ng::vec3(position.x as f32, position.y as f32, -position.z as f32),
+                angle: angle as f32,
+                light: self.map.sectors[sector as usize].light,
+            })
+        };
+
+        let actors = self.actors.iter().filter_map(|(_, actor)| {
+            sprite(&actor.sprite, actor.position, actor.angle, actor.sector)
+        });
+        let pickups = self
+            .pickups
+            .iter()
+            .filter(|pickup| pickup.hidden <= 0.0)
+            .filter_map(|pickup| {
+                let item = &self.items[&pickup.item];
+                sprite(&item.sprite, pickup.position, 0.0, pickup.sector)
+            });
+        // Facing where they fly, gone once they left the map
+        let projectiles = self.projectiles.iter().filter_map(|projectile| {
+            let position = projectile.position;
+            let sector = self.map.sector_at(position.x as f32, position.z as f32)?;
+            let angle = projectile
+                .velocity
+                .z
+                .atan2(projectile.velocity.x)
+                .to_degrees();
+            sprite(&projectile.sprite, position, angle, sector)
+        });
+        actors.chain(pickups).chain(projectiles).collect()
+    }
+}
+
+// Decorations only block shots and take damage if they have health
+fn decoration(thing: &Thing, sprite: &str) -> Actor {
+    let properties = &thing.properties;
+    let mut actor = Actor::new(
+        ActorKind::Decoration,
+        ng::DVec3::new(
+            thing.position.0 as f64,
+            thing.position.1 as f64,
+            thing.position.2 as f64,
+        ),
+        thing.sector,
+        properties["radius"].as_f64().unwrap_or(DECORATION_RADIUS),
+        properties["height"].as_f32().unwrap_or(DECORATION_HEIGHT),
+    );
+    actor.angle = thing.angle as f64;
+    actor.sprite = Some(sprite.to_string());
+    if let Some(health) = properties["health"].as_i32() {
+        actor.vitals = Vitals::new(health);
+        actor.shootable = true;
+    }
+    actor
+}
+
+fn monster(thing: &Thing, def: &EnemyDef) -> Actor {
+    let mut actor = Actor::new(
+        ActorKind::Monster,
+        ng::DVec3::new(
+            thing.position.0 as f64,
+            thing.position.1 as f64,
+            thing.position.2 as f64,
+        ),
+        thing.sector,
+        def.radius,
+        def.height,
+    );
+    actor.angle = thing.angle as f64;
+    actor.vitals = Vitals {
+        resistances: def.resistances,
+        ..Vitals::new(def.health)
+    };
+    actor.shootable = true;
+    actor
+}